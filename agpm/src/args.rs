@@ -6,7 +6,9 @@
 //!```bash
 //!$ amisgitpm install https://github.com/helix-editor/helix
 //!```
-use clap::{Parser, Subcommand};
+use crate::completions::CompletionShell;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 #[derive(Parser, Debug, Clone)]
 #[clap(version, about)]
 /// This struct is the one that takes the arguments from the command line.
@@ -14,18 +16,34 @@ pub struct Cli {
     #[clap(subcommand)]
     /// This argument holds an enum with the different subcommands
     pub com: Commands,
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    /// Show what is being done, with `-vv` and `-vvv` in more detail
+    pub verbose: u8,
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    /// Only show errors
+    pub quiet: bool,
+    #[clap(long, global = true)]
+    /// Also write a detailed log to this file, to attach it when reporting a
+    /// failure
+    pub log_file: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug, Clone)]
 /// This are the possible commands
+// Only one is ever built, so the size of the biggest doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Install a new git repo. It installs from URLs of two kinds.
     ///
     /// 1. git clone URL eg. `https://github.com/helix-editor/helix.git`
     ///
     /// 2. Local git repo URL. `///file:///home/user/Path/to/git/project`
+    ///
+    /// The values of the project that aren't given with the options are asked
+    /// for. When the standard input isn't a terminal nothing is asked, and
+    /// defaults are used instead
     Install {
-        #[clap(value_parser)]
+        #[clap(value_parser, required_unless_present = "locked")]
         /// the git repo url
         /// To install a remote repo
         ///```bash
@@ -40,34 +58,139 @@ pub enum Commands {
         ///amisgitpm install file:///home/user/Path/to/git/project
         ///
         ///```
-        url: String,
+        url: Option<String>,
+        #[clap(long, conflicts_with = "url")]
+        /// Instead of an url, install all the projects of a lockfile written
+        /// with `agpm lock`, each at its locked commit
+        locked: Option<PathBuf>,
+        #[clap(long)]
+        /// The name of the project. By default that of the repository
+        name: Option<String>,
+        #[clap(long)]
+        /// The directory of the project. By default its name
+        dir: Option<String>,
+        #[clap(long = "ref")]
+        /// The reference to follow, like `refs/heads/main` or `main`. By
+        /// default the branch the repository is at
+        ref_string: Option<String>,
+        #[clap(long, value_enum)]
+        /// When to update the project. By default never
+        policy: Option<Policy>,
+        #[clap(long)]
+        /// A step of the build script. It can be repeated, and by default there
+        /// are none
+        install_step: Vec<String>,
+        #[clap(long)]
+        /// A step of the uninstall script. It can be repeated, and by default
+        /// there are none
+        uninstall_step: Vec<String>,
+        #[clap(long, conflicts_with = "install_step")]
+        /// Use the install suggestion with this number, counting from 1, as
+        /// the build script
+        accept_suggestion: Option<usize>,
+        #[clap(long)]
+        /// A project it depends on. It can be repeated, and by default there
+        /// are none
+        depends_on: Vec<String>,
+        #[clap(long, conflicts_with = "locked")]
+        /// A file written with `agpm export` or `agpm lock`. The dependencies
+        /// that aren't installed are taken from it, and installed first
+        deps_from: Option<PathBuf>,
+        #[clap(long)]
+        /// A program needed to build it. It can be repeated, and by default
+        /// those suggested are used
+        requires: Vec<String>,
+        #[clap(long)]
+        /// A tag of the project. It can be repeated, and by default there are
+        /// none
+        tag: Vec<String>,
     },
 
     /// Update project(s)
+    ///
+    /// All of them are fetched at once, and those with new changes are built,
+    /// as many at a time as the `parallelism` of the configuration file
+    /// allows. A failed project doesn't stop the rest, and a table with the
+    /// result of each one is shown at the end
     Update {
         #[clap(value_parser)]
         /// An optional list of project names to update independently.
         ///
         /// If not provided all projects are updated
         project: Vec<String>,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+        #[clap(long, value_enum, default_value_t = Format::Text)]
+        /// How to show the results. With json no questions are asked, and
+        /// without projects only those with the `Always` policy are updated
+        format: Format,
+    },
+
+    /// Show the projects that have new commits upstream, without updating them
+    Outdated {
+        #[clap(value_parser)]
+        /// The projects to check. If not provided all projects are checked
+        project: Vec<String>,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+        #[clap(long, value_enum, default_value_t = Format::Text)]
+        /// How to show the results
+        format: Format,
+    },
+
+    /// Show the space that the projects take in the disk
+    Du {
+        #[clap(value_parser)]
+        /// The projects to measure. If not provided all projects are measured
+        project: Vec<String>,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+        #[clap(long, value_enum, default_value_t = Format::Text)]
+        /// How to show the results
+        format: Format,
     },
 
     /// Update the suggestions, downloading all of them, and substituting those
     /// already present
     UpdateSuggestions,
 
+    /// Create the directories, the configuration file and the store, get the
+    /// suggestions and check that git and a shell are installed. It's done the
+    /// first time agpm runs, and again fixes what is missing
+    Init,
+
     /// Uninstall a project
+    ///
+    /// The projects that depend on others are uninstalled first. A failed
+    /// project doesn't stop the rest, and a table with the result of each one
+    /// is shown at the end
     Uninstall {
         #[clap(value_parser)]
         /// The project name to uninstall
         project: Vec<String>,
+        #[clap(long)]
+        /// Also uninstall all the projects that depend on the given ones
+        cascade: bool,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
     },
 
     /// Get the last version of the project
+    ///
+    /// Projects without a previous version are skipped, and a failed project
+    /// doesn't stop the rest. A table with the result of each one is shown at
+    /// the end
     Restore {
         #[clap(value_parser)]
         /// The project to downgrade
         project: Vec<String>,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
     },
 
     /// Uninstall then install a project
@@ -84,6 +207,20 @@ pub enum Commands {
         project: String,
     },
 
+    /// Run a command in the sources of a project, or open a shell there
+    ///
+    /// The command is run with the same environment as the build scripts, like
+    /// `agpm exec helix -- cargo build --release`. Without a command, `$SHELL`
+    /// is opened
+    Exec {
+        #[clap(value_parser)]
+        /// The name of the project
+        project: String,
+        #[clap(last = true)]
+        /// The command to run and its arguments, after `--`
+        command: Vec<String>,
+    },
+
     /// Remove all srcs with no project associated
     ///
     /// It is `O(N^2)`, with `N` the number of installed projects
@@ -102,8 +239,175 @@ pub enum Commands {
         #[clap(value_parser)]
         /// Packages from which to get detailed information
         project: Vec<String>,
+        #[clap(long)]
+        /// Also select the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+        #[clap(long, value_enum, default_value_t = Format::Text)]
+        /// How to show the projects
+        format: Format,
+    },
+
+    /// Show the state of the repository and the directories of a project
+    Info {
+        #[clap(value_parser)]
+        /// The name of the project
+        project: String,
+        #[clap(long, value_enum, default_value_t = Format::Text)]
+        /// How to show the state
+        format: Format,
+    },
+
+    /// Check that the store and the directories of the projects agree, like
+    /// after an interrupted operation or editing the store by hand
+    ///
+    /// It finds projects without sources, sources and previous versions
+    /// without projects, leftovers of git operations, references that don't
+    /// resolve, directories used by several projects and records of the store
+    /// that can't be read. Each finding is shown with its fix
+    Doctor {
+        #[clap(long)]
+        /// Apply the fix of every finding
+        fix: bool,
     },
 
     /// Install amisgitpm with amisgitpm, check that everything is in place
     Bootstrap,
+
+    /// Show the previous configurations of a project. Only the sqlite store
+    /// backend keeps them
+    History {
+        #[clap(value_parser)]
+        /// The name of the project
+        project: String,
+        #[clap(long)]
+        /// The identifier of a previous configuration to go back to
+        restore: Option<i64>,
+    },
+
+    /// Write the configuration of all the projects to a portable file
+    Export {
+        #[clap(value_parser)]
+        /// The file to write. If not provided, it's written to the standard output
+        file: Option<PathBuf>,
+        #[clap(long)]
+        /// Only write the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+    },
+
+    /// Write the exact commit that was built for each project, next to their
+    /// configuration, to a lockfile. Install them elsewhere with
+    /// `agpm install --locked {file}`
+    Lock {
+        #[clap(value_parser, default_value = "agpm.lock")]
+        /// The lockfile to write
+        file: PathBuf,
+        #[clap(long)]
+        /// Only write the projects that have this tag. It can be repeated
+        tag: Vec<String>,
+    },
+
+    /// Install all the projects of a file written with `agpm export`
+    ///
+    /// Projects that are already installed are skipped, and a failed project
+    /// doesn't stop the rest. A summary is shown at the end
+    Import {
+        #[clap(value_parser)]
+        /// The file to read
+        file: PathBuf,
+    },
+
+    /// Make the installed projects match the projects of a file written with
+    /// `agpm export` or `agpm lock`
+    ///
+    /// Missing projects are installed, projects that aren't in the file are
+    /// uninstalled, and projects whose url, reference or directory changed
+    /// get their sources again. Projects with other changes, like their
+    /// scripts, are edited and built again. The changes are shown before
+    /// being applied
+    Sync {
+        #[clap(value_parser)]
+        /// The file with the desired projects
+        file: PathBuf,
+        #[clap(long)]
+        /// Read the file as a lockfile, getting each project at its commit
+        locked: bool,
+        #[clap(long)]
+        /// Only show the changes, without applying them
+        dry_run: bool,
+    },
+
+    /// Copy all the projects into the store of another backend
+    ///
+    /// After that, set the backend in the configuration file to start using it
+    MigrateStore {
+        #[clap(value_enum)]
+        /// The backend to copy the projects to
+        backend: StoreBackend,
+    },
+
+    /// Write the completion script of a shell to the standard output
+    ///
+    /// For bash and zsh, add `source <(agpm completions bash)` to the
+    /// configuration of the shell. For fish, write it to
+    /// `~/.config/fish/completions/agpm.fish`
+    Completions {
+        #[clap(value_enum)]
+        /// The shell to complete in
+        shell: CompletionShell,
+    },
+
+    /// List the names of the projects, one per line, for the completion scripts
+    #[clap(hide = true)]
+    CompleteProjects,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+/// How the results of a command are shown
+pub enum Format {
+    /// For people to read
+    Text,
+    /// For programs to read, following the schema of the `output` module
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+/// When to update a project
+pub enum Policy {
+    /// Every time that projects are updated
+    Always,
+    /// Asking every time
+    Ask,
+    /// Only when it's given by name
+    Never,
+}
+
+impl From<Policy> for agpm_project::UpdatePolicy {
+    fn from(policy: Policy) -> Self {
+        match policy {
+            Policy::Always => Self::Always,
+            Policy::Ask => Self::Ask,
+            Policy::Never => Self::Never,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+/// The backends that can store the projects
+pub enum StoreBackend {
+    /// A directory of json files
+    Json,
+    /// A single TOML file
+    Toml,
+    /// A SQLite database, that keeps a history of the projects
+    Sqlite,
+}
+
+impl From<StoreBackend> for agpm_store::Backend {
+    fn from(backend: StoreBackend) -> Self {
+        match backend {
+            StoreBackend::Json => Self::Json,
+            StoreBackend::Toml => Self::Toml,
+            StoreBackend::Sqlite => Self::Sqlite,
+        }
+    }
 }
@@ -0,0 +1,126 @@
+//! Completion scripts for the shells.
+//!
+//! The subcommands and their options are completed by the scripts that
+//! `clap_complete` generates. Project names change as projects are installed,
+//! so the commands that take them call the hidden `agpm complete-projects`
+//! subcommand instead, which lists the projects of the store.
+
+use crate::args::Cli;
+use clap::{CommandFactory, ValueEnum};
+use std::io::Write;
+
+/// The shells that completion scripts can be written for
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum CompletionShell {
+    /// Source it with `source <(agpm completions bash)`
+    Bash,
+    /// Source it with `source <(agpm completions zsh)`
+    Zsh,
+    /// Write it to `~/.config/fish/completions/agpm.fish`
+    Fish,
+}
+
+impl From<CompletionShell> for clap_complete::Shell {
+    fn from(shell: CompletionShell) -> Self {
+        match shell {
+            CompletionShell::Bash => Self::Bash,
+            CompletionShell::Zsh => Self::Zsh,
+            CompletionShell::Fish => Self::Fish,
+        }
+    }
+}
+
+/// The subcommands that take project names, and the options of those that
+/// take a value, which isn't a project name
+fn project_commands() -> (Vec<String>, Vec<String>) {
+    let cmd = Cli::command();
+    let commands: Vec<_> = cmd
+        .get_subcommands()
+        .filter(|sub| sub.get_arguments().any(|arg| arg.get_id() == "project"))
+        .collect();
+    let mut valued: Vec<String> = commands
+        .iter()
+        .flat_map(|sub| sub.get_arguments())
+        .filter(|arg| arg.get_long().is_some() && arg.get_action().takes_values())
+        .map(|arg| format!("--{}", arg.get_long().unwrap_or_default()))
+        .collect();
+    valued.sort();
+    valued.dedup();
+    let names = commands.iter().map(|e| e.get_name().to_string()).collect();
+    (names, valued)
+}
+
+/// Write the completion script of `shell` to `out`
+/// # Errors
+/// - Writing to `out`
+pub fn write_completions(shell: CompletionShell, out: &mut impl Write) -> std::io::Result<()> {
+    let mut cmd = Cli::command();
+    let bin = cmd.get_name().to_string();
+    clap_complete::generate(clap_complete::Shell::from(shell), &mut cmd, &bin, out);
+    let (commands, valued) = project_commands();
+    match shell {
+        CompletionShell::Bash => write!(
+            out,
+            r#"
+_{bin}_projects() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    case "${{COMP_WORDS[1]}}" in
+        {commands})
+            case "$cur $prev" in
+                -*|*\ {valued}) ;;
+                *)
+                    COMPREPLY=($(compgen -W "$({bin} complete-projects 2>/dev/null)" -- "$cur"))
+                    return 0
+                    ;;
+            esac
+            ;;
+    esac
+    _{bin} "$@"
+}}
+complete -F _{bin}_projects -o bashdefault -o default {bin}
+"#,
+            commands = commands.join("|"),
+            valued = valued.join("|*\\ "),
+        ),
+        CompletionShell::Zsh => write!(
+            out,
+            r#"
+_{bin}_projects() {{
+    if (( CURRENT > 2 )) && [[ ${{words[2]}} == ({commands}) ]] \
+        && [[ ${{words[CURRENT]}} != -* ]] && [[ ${{words[CURRENT-1]}} != ({valued}) ]]; then
+        local -a projects
+        projects=(${{(f)"$({bin} complete-projects 2>/dev/null)"}})
+        compadd -a projects
+        return
+    fi
+    _{bin} "$@"
+}}
+compdef _{bin}_projects {bin}
+"#,
+            commands = commands.join("|"),
+            valued = valued.join("|"),
+        ),
+        CompletionShell::Fish => writeln!(
+            out,
+            "complete -c {bin} -n \"__fish_seen_subcommand_from {}\" -f -a \"({bin} complete-projects 2>/dev/null)\"",
+            commands.join(" ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completes_projects_of_the_commands_that_take_them() {
+        let (commands, valued) = project_commands();
+        for command in ["update", "uninstall", "edit", "rebuild"] {
+            assert!(commands.iter().any(|e| e == command), "{command}");
+        }
+        assert!(!commands.iter().any(|e| e == "install"));
+        assert!(valued.iter().any(|e| e == "--tag"));
+        assert!(!valued.iter().any(|e| e == "--cascade"));
+    }
+}
@@ -0,0 +1,147 @@
+//! The setup of agpm in a new machine.
+//!
+//! It's done by `agpm init`, and the first time that any other command runs,
+//! which is when there is no configuration file yet. Every step is tried even
+//! if an earlier one fails, and what happened with each of them is reported
+//! with what can be done to fix it.
+
+use crate::{Dirs, ProjectStore};
+use agpm_dirs::DirsConfig;
+use agpm_interactions::InteractConfig;
+use agpm_pm::PMConfig;
+use agpm_store::StoreConfig;
+use agpm_suggestions::{SuggestionsConfig, SuggestionsDirs};
+use amisgitpm::{requirements::Requirement, BatchReport, Directories, Outcome};
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// The configuration file with the default value of every option, to be
+/// edited by hand
+/// # Panics
+/// Not really, the configurations of the crates are always serializable
+pub fn default_config() -> Value {
+    let mut config = json!(PMConfig::default());
+    let map = config.as_object_mut().unwrap(); // It's a struct, so it's an object
+    let interact = json!(InteractConfig::default());
+    map.extend(interact.as_object().unwrap().clone());
+    map.insert("store".into(), json!(StoreConfig::default()));
+    map.insert("dirs".into(), json!(DirsConfig::default()));
+    map.insert("suggestions".into(), json!(SuggestionsConfig::default()));
+    config
+}
+
+/// Whether agpm hasn't been set up yet, as there is no configuration file
+pub fn is_first_run(dirs: &Dirs) -> bool {
+    !dirs.config().exists()
+}
+
+/// Set up agpm, creating what is missing. The outcomes are for each step, and
+/// a failure holds the error and what can be done about it
+pub fn init(dirs: &Dirs) -> BatchReport<String> {
+    let mut report = BatchReport::default();
+    report.push("Create the directories", create_dirs(dirs));
+    report.push("Write the configuration", write_config(&dirs.config()));
+    report.push(
+        "Create the store",
+        match <ProjectStore as amisgitpm::ProjectStore<_>>::new() {
+            Ok(_) => Outcome::Done,
+            Err(e) => Outcome::Failed(format!(
+                "{e}. Check the `store` of the configuration file {:?}",
+                dirs.config()
+            )),
+        },
+    );
+    report.push("Get the suggestions", get_suggestions(dirs));
+    report.push(
+        "Find git",
+        find("git", "Install git, the scripts of many projects need it"),
+    );
+    report.push(
+        "Find a shell",
+        find(
+            "sh",
+            "Install a POSIX shell, the scripts of the projects are run with `sh`",
+        ),
+    );
+    report
+}
+
+fn create_dirs(dirs: &Dirs) -> Outcome<String> {
+    let parents = [dirs.config(), dirs.projects_db()]
+        .into_iter()
+        .filter_map(|path| path.parent().map(Path::to_path_buf));
+    for dir in parents.chain([dirs.src(), dirs.git(), dirs.old()]) {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            return Outcome::Failed(format!(
+                "Unable to create {dir:?}: {e}. Check its permissions, or set another \
+                 directory in the `dirs` of the configuration file"
+            ));
+        }
+    }
+    Outcome::Done
+}
+
+fn write_config(path: &Path) -> Outcome<String> {
+    if path.exists() {
+        return Outcome::Skipped(format!("{path:?} already exists"));
+    }
+    // Pretty printing a json value can't fail
+    let content = serde_json::to_string_pretty(&default_config()).unwrap();
+    match std::fs::write(path, content + "\n") {
+        Ok(()) => Outcome::Done,
+        Err(e) => Outcome::Failed(format!(
+            "Unable to write {path:?}: {e}. Check the permissions of its directory"
+        )),
+    }
+}
+
+fn get_suggestions(dirs: &Dirs) -> Outcome<String> {
+    let dir = dirs.suggestions();
+    if dir.exists() {
+        return Outcome::Skipped(format!("{dir:?} already exists"));
+    }
+    if let Err(e) = agpm_suggestions::download_resources::<Dirs>() {
+        tracing::warn!(error = %e, "Unable to download the suggestions, unpacking the bundled ones");
+        if let Err(e) = agpm_suggestions::unpack_resources(&dir) {
+            return Outcome::Failed(format!(
+                "{e}. Check the permissions of {dir:?}, then get them with \
+                 `agpm update-suggestions`"
+            ));
+        }
+    }
+    Outcome::Done
+}
+
+fn find(executable: &str, hint: &str) -> Outcome<String> {
+    let requirement = Requirement {
+        executable: executable.into(),
+        min_version: None,
+    };
+    match requirement.find() {
+        Some(_) => Outcome::Done,
+        None => Outcome::Failed(format!("{executable} wasn't found in the PATH. {hint}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_config_can_be_read() {
+        let file = std::env::temp_dir().join(format!("agpm-init-{}.json", std::process::id()));
+        let Outcome::Done = write_config(&file) else {
+            panic!("The configuration wasn't written");
+        };
+        assert!(matches!(write_config(&file), Outcome::Skipped(_)));
+        let pm = PMConfig::load(&file).unwrap();
+        assert_eq!(pm.generations, PMConfig::default().generations);
+        let store = StoreConfig::load::<std::io::Error>(&file).unwrap();
+        assert_eq!(store.backend, agpm_store::Backend::Json);
+        let config: Value = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        serde_json::from_value::<InteractConfig>(config.clone()).unwrap();
+        serde_json::from_value::<SuggestionsConfig>(config["suggestions"].clone()).unwrap();
+        serde_json::from_value::<DirsConfig>(config["dirs"].clone()).unwrap();
+        std::fs::remove_file(&file).unwrap();
+    }
+}
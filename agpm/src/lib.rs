@@ -5,17 +5,25 @@ pub use agpm_dirs::Dirs;
 use agpm_interactions::Interactor;
 use agpm_pm::PrjManager;
 pub use agpm_project::{Project, UpdatePolicy};
-use agpm_store::Store;
+use agpm_store::AnyStore;
 
 /// The interactor thats used coordinating the `Interactor` and the `Dirs` structures.
 pub type Interacts = Interactor<Dirs>;
-/// The store thats created using the `Store`, `Project, and the `Dirs` structures
-pub type ProjectStore = Store<Dirs, Project>;
+/// The store thats created using the `AnyStore`, `Project, and the `Dirs` structures.
+/// Its backend is chosen in the configuration file
+pub type ProjectStore = AnyStore<Dirs, Project>;
 /// The manager thats created from the `Project`, the `Dirs` structure and the
 /// `ProjectStore` and `Interacts` types
 pub type ProjectManager = PrjManager<Project, Dirs, ProjectStore, Interacts>;
+/// The errors of the `ProjectManager`. Each one has a hint, with what can be
+/// done to solve it, and a class, that gives the exit code of the commands
+pub type ManagerError = <ProjectManager as amisgitpm::PMOperations>::Error;
 
 pub mod args;
+pub mod completions;
+pub mod init;
+pub mod logging;
+pub mod output;
 
 /// A module with all the important traits that will be needed to use the above public types
 pub mod prelude {
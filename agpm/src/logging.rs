@@ -0,0 +1,97 @@
+//! The logs of the operations of the project manager.
+//!
+//! The library crates emit `tracing` events for cloning, fetching, copying,
+//! building and writing to the store, with how long each one took. They are
+//! shown in the standard error, at the level chosen with `-v` and `-q`, and
+//! written to a log file at least at the debug level.
+
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{
+    field::RecordFields,
+    filter::Targets,
+    fmt::{self, format::DefaultFields, format::Writer, FormatFields},
+    prelude::*,
+};
+
+/// The fields of the events of the log file. The fields of the spans are
+/// formatted once per formatter type, so without a type of its own the log file
+/// would get the colored fields of the standard error
+struct PlainFields(DefaultFields);
+
+impl<'w> FormatFields<'w> for PlainFields {
+    fn format_fields<R: RecordFields>(&self, writer: Writer<'w>, fields: R) -> std::fmt::Result {
+        self.0.format_fields(writer, fields)
+    }
+}
+
+/// The level of the events shown in the standard error. Warnings by default,
+/// one level more for each `-v`, and only errors with `-q`
+pub fn level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::ERROR,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    }
+}
+
+/// Only the events of the project manager, not those of its dependencies
+fn targets(level: LevelFilter) -> Targets {
+    Targets::new()
+        .with_target("amisgitpm", level)
+        .with_target("agpm", level)
+}
+
+/// Start showing the events up to `level`, and writing them to `log_file`,
+/// which is appended to
+/// # Errors
+/// - Unable to open the log file
+pub fn init(level: LevelFilter, log_file: Option<&Path>) -> std::io::Result<()> {
+    let stderr = fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_target(false)
+        .with_filter(targets(level));
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let layer = fmt::layer()
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .fmt_fields(PlainFields(DefaultFields::new()))
+                .with_filter(targets(level.max(LevelFilter::DEBUG)));
+            Some(layer)
+        }
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .init();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn verbosity_flags_choose_the_level() {
+        let level_of = |args: &[&str]| {
+            let cli = Cli::try_parse_from(args).unwrap();
+            level(cli.verbose, cli.quiet)
+        };
+        assert_eq!(level_of(&["agpm", "list"]), LevelFilter::WARN);
+        assert_eq!(level_of(&["agpm", "list", "-vv"]), LevelFilter::DEBUG);
+        assert_eq!(level_of(&["agpm", "-vvvv", "list"]), LevelFilter::TRACE);
+        assert_eq!(level_of(&["agpm", "list", "-q"]), LevelFilter::ERROR);
+        assert!(Cli::try_parse_from(["agpm", "list", "-q", "-v"]).is_err());
+    }
+}
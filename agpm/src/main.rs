@@ -1,187 +1,575 @@
 use clap::Parser;
-use color_eyre::eyre::{eyre, Result};
+use color_eyre::eyre::{eyre, Report, Result, WrapErr};
+use std::process::ExitCode;
 
 use agpm::{
-    args::{Cli, Commands},
+    args::{Cli, Commands, Format},
+    output::{self, DuEntry, InfoEntry, ListEntry, OutdatedEntry, Output, UpdateEntry},
     prelude::*,
-    Dirs, Project, ProjectManager, UpdatePolicy,
+    Dirs, ManagerError, Project, ProjectManager, ProjectStore, UpdatePolicy,
 };
-use agpm_pm::PMError;
-use amisgitpm::CommonPMErrors;
+use agpm_interactions::InstallPresets;
+use agpm_store::{Backend, StoreConfig};
+use amisgitpm::{BatchReport, CommonPMErrors, ErrorClass, Outcome};
 
 const NO_FIX: &str = "Currently no fixes are available for your error";
 
-const NON_EXIST: (&str, &str) = (
-    "There are no projects installed with name",
-    "Use `agpm list` to see all available projects",
-);
+/// Some projects of a batch operation failed, and the rest were done. Holds
+/// the operation and the projects that failed
+#[derive(Debug)]
+struct BatchFailed(&'static str, Vec<String>);
 
-fn main() -> Result<()> {
+impl std::error::Error for BatchFailed {}
+impl std::fmt::Display for BatchFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to {} the projects {:?}", self.0, self.1)
+    }
+}
+
+/// Add to an error of the manager what can be done to solve it
+fn hinted(e: ManagerError) -> Report {
+    match e.hint() {
+        Some(hint) => eyre!(e).wrap_err(hint),
+        None => eyre!(e).wrap_err(NO_FIX),
+    }
+}
+
+/// The exit code of the command for an error, from its class
+fn exit_code(report: &Report) -> u8 {
+    let class = if report.downcast_ref::<BatchFailed>().is_some() {
+        ErrorClass::Partial
+    } else {
+        report
+            .downcast_ref::<ManagerError>()
+            .map_or(ErrorClass::Other, ManagerError::class)
+    };
+    class.exit_code()
+}
+
+/// Print what happened to each project of a batch operation, failing if any
+/// project failed, with the hint of each failure. `operation` is what was done,
+/// like `update`, and `done` the word for the projects with which it was done,
+/// like `updated`. `retry` is the command that tries them again
+fn check_report(
+    report: BatchReport<ManagerError>,
+    operation: &'static str,
+    done: &str,
+    retry: Option<String>,
+) -> Result<()> {
+    print!("{}", output::summary_table(&report, done));
+    if !report.has_failures() {
+        return Ok(());
+    }
+    let hints: String = report
+        .outcomes
+        .iter()
+        .filter_map(|(name, outcome)| match outcome {
+            Outcome::Failed(e) => Some(format!(
+                "- {name}: {}\n",
+                e.hint().unwrap_or_else(|| NO_FIX.into())
+            )),
+            _ => None,
+        })
+        .collect();
+    let failed = report.failed().iter().map(|e| e.to_string()).collect();
+    let retry = retry.map_or(String::new(), |retry| {
+        format!("Then try them again with\n`{retry}`")
+    });
+    Err(BatchFailed(operation, failed)).wrap_err(format!(
+        "The rest of the projects were {done}.\n{hints}{retry}"
+    ))
+}
+
+/// Get the projects with the given names, or all of them if there are none
+fn get_selected<'a>(pm: &'a ProjectManager, project: &[String]) -> Result<Vec<&'a Project>> {
+    if project.is_empty() {
+        return Ok(pm.get_all());
+    }
+    project
+        .iter()
+        .map(|name| {
+            pm.get_one(name)
+                .ok_or_else(|| hinted(CommonPMErrors::NonExisting(name.clone()).into()))
+        })
+        .collect()
+}
+
+/// Add the projects that have any of the tags of a command to the projects of
+/// the command
+fn select_tagged(pm: &ProjectManager, com: Commands) -> Result<Commands> {
+    let add_tagged = |mut project: Vec<String>, tag: Vec<String>| {
+        if tag.is_empty() {
+            return Ok(project);
+        }
+        let tagged = pm.get_tagged(&tag);
+        if tagged.is_empty() {
+            Err(eyre!("There are no projects tagged {tag:?}"))
+                .wrap_err("Use `agpm list` to see the tags of the projects")?;
+        }
+        for prj in tagged {
+            if !project.contains(&prj.name) {
+                project.push(prj.name.clone());
+            }
+        }
+        Ok::<_, color_eyre::Report>(project)
+    };
+    Ok(match com {
+        Commands::Update {
+            project,
+            tag,
+            format,
+        } => Commands::Update {
+            project: add_tagged(project, tag)?,
+            tag: vec![],
+            format,
+        },
+        Commands::Outdated {
+            project,
+            tag,
+            format,
+        } => Commands::Outdated {
+            project: add_tagged(project, tag)?,
+            tag: vec![],
+            format,
+        },
+        Commands::Du {
+            project,
+            tag,
+            format,
+        } => Commands::Du {
+            project: add_tagged(project, tag)?,
+            tag: vec![],
+            format,
+        },
+        Commands::Uninstall {
+            project,
+            cascade,
+            tag,
+        } => Commands::Uninstall {
+            project: add_tagged(project, tag)?,
+            cascade,
+            tag: vec![],
+        },
+        Commands::Restore { project, tag } => Commands::Restore {
+            project: add_tagged(project, tag)?,
+            tag: vec![],
+        },
+        Commands::List {
+            project,
+            tag,
+            format,
+        } => Commands::List {
+            project: add_tagged(project, tag)?,
+            tag: vec![],
+            format,
+        },
+        com => com,
+    })
+}
+
+/// Find, and with `fix` repair, what doesn't agree between the store and the
+/// directories. The records of the store are checked before opening it, as an
+/// unreadable record keeps it from being opened
+fn doctor(fix: bool) -> Result<()> {
+    let dirs = open_dirs()?;
+    let mut report: BatchReport<color_eyre::Report> = BatchReport::default();
+    let mut found = 0;
+    if StoreConfig::load::<agpm_dirs::DirError>(&dirs.config())?.backend == Backend::Json {
+        for record in agpm_store::Store::<Dirs, Project>::unreadable_records()? {
+            found += 1;
+            println!("{record}\n    Fix: Move the record out of the store, to fix it by hand");
+            if fix {
+                match record.set_aside() {
+                    Ok(path) => {
+                        println!("    The record is now at {path:?}");
+                        report.push(&record.name, Outcome::Done);
+                    }
+                    Err(e) => {
+                        println!("    Failed: {e}");
+                        report.push(&record.name, Outcome::Failed(eyre!(e)));
+                    }
+                }
+            }
+        }
+    }
+    if !fix && found > 0 {
+        Err(eyre!("Found {found} unreadable records")).wrap_err(
+            "Set them aside with `agpm doctor --fix`. The rest of the checks need to read the store",
+        )?;
+    }
+    let mut pm = ProjectManager::new().map_err(|e| {
+        eyre!(e).wrap_err(format!(
+            "The store can't be opened. Fix or remove the file that is reported in
+    - {:?}",
+            dirs.projects_db()
+        ))
+    })?;
+    for finding in pm.diagnose()? {
+        found += 1;
+        println!("{finding}\n    Fix: {}", finding.fix());
+        if !fix {
+            continue;
+        }
+        match pm.repair(&finding) {
+            Ok(removed) if !removed.is_empty() => {
+                let file = dirs
+                    .config()
+                    .with_file_name(format!("doctor-{}.json", removed[0].dir));
+                agpm_store::write_bundle(&removed, std::fs::File::create(&file)?)?;
+                println!(
+                    "    Their configuration is at {file:?}. Change their directories and run
+    `agpm import {file:?}`"
+                );
+                report.push(finding.to_string(), Outcome::Done);
+            }
+            Ok(_) => report.push(finding.to_string(), Outcome::Done),
+            Err(e) => {
+                println!("    Failed: {e}");
+                report.push(finding.to_string(), Outcome::Failed(eyre!(e)));
+            }
+        }
+    }
+    if found == 0 {
+        println!("The store and the directories agree");
+    } else if !fix {
+        Err(eyre!("Found {found} problems")).wrap_err("Fix them with `agpm doctor --fix`")?;
+    } else if report.has_failures() {
+        Err(eyre!("Failed to fix {:?}", report.failed()))
+            .wrap_err("Run `agpm doctor` again to see what is left")?;
+    }
+    Ok(())
+}
+
+/// Get the directories of agpm, telling what to do if they can't be found
+fn open_dirs() -> Result<Dirs> {
+    Dirs::new().map_err(|e| {
+        let hint = match e {
+            agpm_dirs::DirError::HomeNotFound => {
+                "Set `$HOME` to the home directory of the user, agpm keeps its files there"
+            }
+            _ => "Fix the configuration file, or remove it to use the defaults",
+        };
+        eyre!(e).wrap_err(hint)
+    })
+}
+
+/// Set up agpm, printing what happened with each step, and failing if any
+/// of them failed
+fn set_up(dirs: &Dirs) -> Result<()> {
+    let report = agpm::init::init(dirs);
+    for (step, outcome) in &report.outcomes {
+        eprintln!("{step}: {outcome}");
+    }
+    if report.has_failures() {
+        Err(eyre!(
+            "Unable to set up agpm, failed to {:?}",
+            report.failed()
+        ))
+        .wrap_err("Fix the problems above and run `agpm init` again")?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(report) => {
+            eprintln!("Error: {report:?}");
+            ExitCode::from(exit_code(&report))
+        }
+    }
+}
+
+fn run() -> Result<()> {
     color_eyre::install()?;
     let args = Cli::parse();
-    let mut pm = ProjectManager::new().unwrap();
-    match args.com {
-        Commands::Install { url } => pm.i_install(&url).map_err(|e| match e {
-            PMError::Git(_) | PMError::Store(_) => eyre!(e).wrap_err(
-                "Had a git or store error while installing. If the url is correct, run
-`agpm clean`
-and then install again",
-            ),
-            PMError::FileExt(_) => eyre!(e).wrap_err(format!(
-                "Error while moving files.
-Check for read and write permissions in the directories:
-    - {:?}
-    - {:?}
-Then manually move the files from the first directory to the second and run:
-`agpm rebuild {{your project name}}`",
-                Dirs::new().unwrap().git(),
-                Dirs::new().unwrap().src()
-            )),
-            PMError::Spawn(_) | PMError::Exec => eyre!(e).wrap_err(
-                "Had some illegal arguments or problems with io, or failed at building.
-Please edit with:
-`agpm edit {{your project name}}`
-And then run run:
-`agpm rebuild {{your project name}}`",
-            ),
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::Uninstall { project } => pm.i_uninstall(&project).map_err(|e| match e {
-            PMError::Spawn(_) | PMError::Exec => eyre!(e).wrap_err(
-                "Had some illegal arguments or problems with io, or failed at building.
-Please edit with:
-`agpm edit {{project that failed}}`
-And then run run:
-`agpm uninstall {{all not uninstalled projects}}`",
-            ),
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            PMError::IO(_) => eyre!(e).wrap_err(format!(
-                "Error while erasing files, check the permissions for the directories:
-    - {:?}
-    - {:?}
-and run again. If you run into this same problem and you know that the files are
-erased, then go to:
-    - {:?}
-and manually delete the file that has the name of the program that's giving you trouble
-",
-                Dirs::new().unwrap().src(),
-                Dirs::new().unwrap().old(),
-                Dirs::new().unwrap().projects_db()
-            )),
-            PMError::Store(_) => eyre!(e).wrap_err(format!(
-                "Had a store error go to:
-    - {:?}
-and manually delete the file that has the name of the program that's giving you trouble",
-                Dirs::new().unwrap().projects_db()
-            )),
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::Update { project } => pm.i_update(&project).map_err(|e| match e {
-            PMError::Git(e) => eyre!(e).wrap_err(format!(
-                "Error while updating with git.
-Solve the git problems manually in the corresponding directory in:
-    - {:?}
-Then manually move it to:
-    - {:?}
-and run
-`agpm rebuild {{your project name}}`",
-                Dirs::new().unwrap().git(),
-                Dirs::new().unwrap().src(),
-            )),
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            PMError::FileExt(_) => eyre!(e).wrap_err(format!(
-                "Error while move files, check the permissions for the directories:
-    - {:?}
-    - {:?}
-    - {:?}
-and run again.
-",
-                Dirs::new().unwrap().git(),
-                Dirs::new().unwrap().src(),
-                Dirs::new().unwrap().old(),
-            )),
-            PMError::Spawn(_) | PMError::Exec => eyre!(e).wrap_err(
-                "Had some illegal arguments or problems with io, or failed at building.
-Please edit with:
-`agpm edit {{project that failed}}`
-And then run run:
-`agpm update {{all not updated projects}}`",
-            ),
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::Restore { project } => pm.i_restore(&project).map_err(|e| match e {
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            PMError::FileExt(_) => eyre!(e).wrap_err(format!(
-                "Error while move files, check the permissions for the directories:
-    - {:?}
-    - {:?}
-and run again.
-",
-                Dirs::new().unwrap().old(),
-                Dirs::new().unwrap().src(),
-            )),
-            PMError::IO(_) => eyre!(e).wrap_err(format!(
-                "Error while erasing files, check the permissions in the files:
-    - {:?}
-and run again.",
-                Dirs::new().unwrap().src(),
-            )),
-            PMError::Spawn(_) | PMError::Exec => eyre!(e).wrap_err(
-                "Had some illegal arguments or problems with io, or failed at building.
-Please edit with:
-`agpm edit {{project that failed}}`
-And then run run:
-`agpm restore {{all not restored projects}}`",
-            ),
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::Reinstall { project } => pm
-            .reinstall(project)
-            .map_err(|e| eyre!(e).wrap_err("Running a composed command, can't separate errors"))?,
-        Commands::Rebuild { project } => pm.rebuild(&project).map_err(|e| match e {
-            PMError::Spawn(_) | PMError::Exec => eyre!(e).wrap_err(format!(
-                "Had some illegal arguments or problems with io, or failed at building.
-Please edit with:
-`agpm edit {project}`
-And then run run:
-`agpm restore {project}`",
-            )),
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::List { project } => pm.i_list(&project).map_err(|e| match e {
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
-        Commands::Edit { project } => pm.i_edit(&project).map_err(|e| match e {
-            PMError::Common(CommonPMErrors::NonExisting) => {
-                eyre!(e).wrap_err(format!("{} {project:?}\n{}", NON_EXIST.0, NON_EXIST.1))
-            }
-            _ => eyre!(e).wrap_err("There was some error while editing, try again, please"),
-        })?,
-        Commands::Clean => pm.cleanup().map_err(|e| match e {
-            PMError::IO(_) => eyre!(e).wrap_err(format!(
-                "Error while erasing files, check the permissions for the directories:
-    - {:?}
-    - {:?}
-    - {:?}
-and their subdirectories run again.",
-                Dirs::new().unwrap().src(),
-                Dirs::new().unwrap().git(),
-                Dirs::new().unwrap().old(),
-            )),
-            _ => eyre!(e).wrap_err(NO_FIX),
-        })?,
+    agpm::logging::init(
+        agpm::logging::level(args.verbose, args.quiet),
+        args.log_file.as_deref(),
+    )
+    .wrap_err("Unable to open the log file")?;
+    if let Commands::Completions { shell } = args.com {
+        agpm::completions::write_completions(shell, &mut std::io::stdout())?;
+        return Ok(());
+    }
+    if let Commands::Doctor { fix } = args.com {
+        return doctor(fix);
+    }
+    let dirs = open_dirs()?;
+    if let Commands::Init = args.com {
+        return set_up(&dirs);
+    }
+    if !matches!(args.com, Commands::CompleteProjects) && agpm::init::is_first_run(&dirs) {
+        eprintln!("Setting up agpm, as it's the first time it runs");
+        set_up(&dirs)?;
+    }
+    let mut pm = ProjectManager::new().map_err(hinted)?;
+    match select_tagged(&pm, args.com)? {
+        Commands::Install {
+            locked: Some(file), ..
+        } => {
+            let prjs: Vec<(Project, String)> =
+                agpm_store::read_lockfile(std::fs::File::open(&file)?)?;
+            let report = pm
+                .install_batch_at(prjs.into_iter().map(|(prj, c)| (prj, Some(c))).collect())
+                .map_err(hinted)?;
+            check_report(
+                report,
+                "install",
+                "installed",
+                Some(format!("agpm install --locked {file:?}")),
+            )?;
+        }
+        Commands::Install { url: None, .. } => Err(eyre!("Give an url or a lockfile to install"))?,
+        Commands::Install {
+            url: Some(url),
+            name,
+            dir,
+            ref_string,
+            policy,
+            install_step,
+            uninstall_step,
+            accept_suggestion,
+            depends_on,
+            deps_from,
+            requires,
+            tag,
+            ..
+        } => {
+            let available: Vec<Project> = match deps_from {
+                Some(file) => agpm_store::read_bundle(std::fs::File::open(file)?)?,
+                None => vec![],
+            };
+            let given = |values: Vec<String>| (!values.is_empty()).then_some(values);
+            let presets = InstallPresets {
+                name,
+                dir,
+                ref_string,
+                update_policy: policy.map(Into::into),
+                install_script: given(install_step),
+                uninstall_script: given(uninstall_step),
+                accept_suggestion,
+                dependencies: given(depends_on),
+                tags: given(tag),
+                requirements: given(requires),
+            };
+            pm.i_install_with(&url, presets, &available)
+                .map_err(hinted)?
+        }
+        Commands::Uninstall {
+            project, cascade, ..
+        } => {
+            let mut names = project.clone();
+            if cascade {
+                for prj in &project {
+                    pm.get_one(prj)
+                        .ok_or_else(|| hinted(CommonPMErrors::NonExisting(prj.clone()).into()))?;
+                    for dependent in pm.get_dependents(prj).map_err(hinted)? {
+                        if !names.contains(&dependent.name) {
+                            names.push(dependent.name.clone());
+                        }
+                    }
+                }
+            }
+            let report = pm.i_uninstall(&names).map_err(hinted)?;
+            // The skipped projects are tried again with those that failed
+            let retry: Vec<&str> = report
+                .outcomes
+                .iter()
+                .filter(|(_, outcome)| !matches!(outcome, Outcome::Done))
+                .map(|(name, _)| name.as_str())
+                .collect();
+            let retry = format!("agpm uninstall {}", retry.join(" "));
+            check_report(report, "uninstall", "uninstalled", Some(retry))?;
+        }
+        Commands::Update {
+            project,
+            format: Format::Json,
+            ..
+        } => {
+            // Only the json goes to stdout
+            pm.scripts_to_stderr(true);
+            // Without questions, only the projects that always update are
+            // updated when none are given
+            let (project, held) = if project.is_empty() {
+                let (always, held): (Vec<&Project>, Vec<&Project>) = pm
+                    .get_all()
+                    .into_iter()
+                    .partition(|prj| prj.update_policy == UpdatePolicy::Always);
+                (always.iter().map(|prj| prj.name.clone()).collect(), held)
+            } else {
+                (project, vec![])
+            };
+            let mut report = pm.update_batch(&project).map_err(hinted)?;
+            for prj in held {
+                let reason = format!("the update policy is {:?}", prj.update_policy);
+                report.push(&prj.name, Outcome::Skipped(reason));
+            }
+            Output::new(UpdateEntry::from_report(&report)).print()?;
+            if report.has_failures() {
+                let failed = report.failed().iter().map(|e| e.to_string()).collect();
+                Err(BatchFailed("update", failed))?;
+            }
+        }
+        Commands::Update { project, .. } => {
+            let report = pm.i_update(&project).map_err(hinted)?;
+            let retry = format!("agpm update {}", report.failed().join(" "));
+            check_report(report, "update", "updated", Some(retry))?;
+        }
+        Commands::Restore { project, .. } => {
+            let report = pm.i_restore(&project).map_err(hinted)?;
+            check_report(report, "restore", "restored", None)?;
+        }
+        Commands::Reinstall { project } => pm.reinstall(project).map_err(hinted)?,
+        Commands::Rebuild { project } => pm.rebuild(&project).map_err(hinted)?,
+        Commands::Exec { project, command } => {
+            let status = pm.exec(&project, &command).map_err(hinted)?;
+            // The exit code of the command is that of agpm
+            match status {
+                subprocess::ExitStatus::Exited(0) => {}
+                subprocess::ExitStatus::Exited(code) => std::process::exit(code as i32),
+                subprocess::ExitStatus::Signaled(signal) => std::process::exit(128 + signal as i32),
+                _ => std::process::exit(1),
+            }
+        }
+        Commands::List {
+            project,
+            format: Format::Json,
+            ..
+        } => Output::new(
+            get_selected(&pm, &project)?
+                .into_iter()
+                .map(|prj| ListEntry::new(prj, pm.get_commit(prj).ok()))
+                .collect(),
+        )
+        .print()?,
+        Commands::Outdated {
+            project, format, ..
+        } => {
+            let entries: Vec<OutdatedEntry> = get_selected(&pm, &project)?
+                .into_iter()
+                .map(|prj| {
+                    let (outdated, installed, latest, error) = match pm.outdated(&prj.name) {
+                        Ok(Some((installed, latest))) => {
+                            (true, Some(installed), Some(latest), None)
+                        }
+                        Ok(None) => (false, pm.get_commit(prj).ok(), None, None),
+                        Err(e) => (false, pm.get_commit(prj).ok(), None, Some(e.to_string())),
+                    };
+                    OutdatedEntry {
+                        name: prj.name.clone(),
+                        outdated,
+                        installed,
+                        latest,
+                        error,
+                    }
+                })
+                .collect();
+            let failed: Vec<String> = entries
+                .iter()
+                .filter(|entry| entry.error.is_some())
+                .map(|entry| entry.name.clone())
+                .collect();
+            match format {
+                Format::Json => Output::new(entries).print()?,
+                Format::Text => {
+                    let short = |commit: &Option<String>| {
+                        commit
+                            .as_deref()
+                            .unwrap_or("?")
+                            .chars()
+                            .take(8)
+                            .collect::<String>()
+                    };
+                    for entry in entries.iter() {
+                        if let Some(error) = &entry.error {
+                            println!("{}: failed, {error}", entry.name);
+                        } else if entry.outdated {
+                            let (from, to) = (short(&entry.installed), short(&entry.latest));
+                            println!("{}: {from} -> {to}", entry.name);
+                        }
+                    }
+                    if failed.is_empty() && !entries.iter().any(|entry| entry.outdated) {
+                        println!("The projects are up to date");
+                    }
+                }
+            }
+            if !failed.is_empty() {
+                Err(eyre!("Unable to check the projects {failed:?}")).wrap_err(
+                    "Check their urls with `agpm list` and your connection, and try again",
+                )?;
+            }
+        }
+        Commands::Du {
+            project, format, ..
+        } => {
+            let entries = get_selected(&pm, &project)?
+                .into_iter()
+                .map(|prj| Ok(DuEntry::new(&prj.name, pm.disk_usage(&prj.name)?)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(hinted)?;
+            match format {
+                Format::Json => Output::new(entries).print()?,
+                Format::Text => {
+                    let width = entries
+                        .iter()
+                        .map(|e| e.name.len())
+                        .max()
+                        .unwrap_or(0)
+                        .max(5);
+                    for entry in entries.iter() {
+                        println!(
+                            "{:<width$} {:>10} (sources {}, previous version {})",
+                            entry.name,
+                            output::human_size(entry.total),
+                            output::human_size(entry.src),
+                            output::human_size(entry.old),
+                        );
+                    }
+                    let total = entries.iter().map(|e| e.total).sum();
+                    println!("{:<width$} {:>10}", "total", output::human_size(total));
+                }
+            }
+        }
+        Commands::Info { project, format } => {
+            let info = pm.info(&project).map_err(hinted)?;
+            match format {
+                Format::Json => Output::new(vec![InfoEntry::from(info)]).print()?,
+                Format::Text => {
+                    println!("Name:           {}", info.name);
+                    println!("Sources:        {}", info.src.display());
+                    println!("Url:            {}", info.url);
+                    if !info.remote_matches() {
+                        let remote = info.remote_url.as_deref().unwrap_or("none");
+                        println!("Remote url:     {remote}, which isn't the url above");
+                    }
+                    println!("Reference:      {}", info.ref_string);
+                    let branch = info.branch.as_deref().unwrap_or("none, detached");
+                    println!("Branch:         {branch}");
+                    match &info.describe {
+                        Some(describe) => println!("Commit:         {} ({describe})", info.commit),
+                        None => println!("Commit:         {}", info.commit),
+                    }
+                    let tree = if info.dirty { "changed" } else { "clean" };
+                    println!("Working tree:   {tree}");
+                    let fetch = info.last_fetch.map_or("never".into(), output::ago);
+                    println!("Last fetch:     {fetch}");
+                    match info.generations.first() {
+                        Some(commit) => println!("Restorable:     {commit}"),
+                        None => println!("Restorable:     nothing"),
+                    }
+                    println!(
+                        "Disk usage:     {} (sources {}, previous version {})",
+                        output::human_size(info.disk_usage.total()),
+                        output::human_size(info.disk_usage.src),
+                        output::human_size(info.disk_usage.old),
+                    );
+                }
+            }
+        }
+        Commands::List { project, .. } => pm.i_list(&project).map_err(hinted)?,
+        Commands::Edit { project } => pm.i_edit(&project).map_err(hinted)?,
+        Commands::Clean => pm.cleanup().map_err(hinted)?,
         Commands::Bootstrap => {
             println!("Using the manager to install the manager");
             let prj = Project {
@@ -192,10 +580,127 @@ and their subdirectories run again.",
                 update_policy: UpdatePolicy::Always,
                 install_script: vec!["cargo install --path ./agpm".into()],
                 uninstall_script: vec!["cargo uninstall agpm".into()],
+                ..Default::default()
             };
-            pm.install(prj)?;
+            pm.install(prj).map_err(hinted)?;
             agpm_suggestions::download_resources::<Dirs>()?;
         }
+        Commands::History { project, restore } => {
+            let store = pm.get_store();
+            match restore {
+                Some(id) => {
+                    let history = store
+                        .history(&project)
+                        .map_err(|e| hinted(ProjectManager::map_store_error(e)))?;
+                    let Some(entry) = history.into_iter().find(|entry| entry.id == id) else {
+                        Err(eyre!("The configuration #{id} isn't one of {project:?}")).wrap_err(
+                            format!("See its configurations with `agpm history {project}`"),
+                        )?
+                    };
+                    // It keeps the name it has now, even if it was renamed since
+                    let prj = Project {
+                        name: project.clone(),
+                        ..entry.project
+                    };
+                    pm.edit(&project, prj).map_err(hinted)?;
+                }
+                None => {
+                    for entry in store
+                        .history(&project)
+                        .map_err(|e| hinted(ProjectManager::map_store_error(e)))?
+                    {
+                        println!(
+                            "#{} {:>6} at {}: {} {} {}",
+                            entry.id,
+                            entry.action,
+                            entry.time,
+                            entry.project.name,
+                            entry.project.url,
+                            entry.project.ref_string
+                        );
+                    }
+                }
+            }
+        }
+        Commands::Export { file, tag } => {
+            let prjs = if tag.is_empty() {
+                pm.get_all()
+            } else {
+                pm.get_tagged(&tag)
+            };
+            match file {
+                Some(path) => agpm_store::write_bundle(prjs, std::fs::File::create(path)?)?,
+                None => agpm_store::write_bundle(prjs, std::io::stdout())?,
+            }
+        }
+        Commands::Import { file } => {
+            let prjs: Vec<Project> = agpm_store::read_bundle(std::fs::File::open(&file)?)?;
+            let report = pm.install_batch(prjs).map_err(hinted)?;
+            check_report(
+                report,
+                "install",
+                "installed",
+                Some(format!("agpm import {file:?}")),
+            )?;
+        }
+        Commands::Sync {
+            file,
+            locked,
+            dry_run,
+        } => {
+            let desired: Vec<(Project, Option<String>)> = if locked {
+                agpm_store::read_lockfile(std::fs::File::open(&file)?)?
+                    .into_iter()
+                    .map(|(prj, commit)| (prj, Some(commit)))
+                    .collect()
+            } else {
+                agpm_store::read_bundle(std::fs::File::open(&file)?)?
+                    .into_iter()
+                    .map(|prj| (prj, None))
+                    .collect()
+            };
+            let changes = pm.plan_sync(desired).map_err(hinted)?;
+            if changes.is_empty() {
+                println!("The projects are already in sync");
+            }
+            changes.iter().for_each(|change| println!("{change}"));
+            if !dry_run && !changes.is_empty() {
+                let locked = if locked { " --locked" } else { "" };
+                check_report(
+                    pm.apply_sync(changes),
+                    "sync",
+                    "synced",
+                    Some(format!("agpm sync{locked} {file:?}")),
+                )?;
+            }
+        }
+        Commands::Lock { file, tag } => {
+            let prjs = if tag.is_empty() {
+                pm.get_all()
+            } else {
+                pm.get_tagged(&tag)
+            };
+            let locked = prjs
+                .into_iter()
+                .map(|prj| Ok((prj, pm.get_commit(prj)?)))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(hinted)?;
+            agpm_store::write_lockfile(locked, std::fs::File::create(&file)?)?;
+        }
+        Commands::MigrateStore { backend } => {
+            let backend = backend.into();
+            if pm.get_store().backend() == backend {
+                Err(eyre!("The store already uses the {backend:?} backend"))?;
+            }
+            let mut to = ProjectStore::open(backend)?;
+            let copied = agpm_store::copy_store(pm.get_store(), &mut to)?;
+            println!(
+                "Copied {copied} projects. To use them, set the store backend in {:?}",
+                dirs.config()
+            );
+        }
+        Commands::Completions { .. } | Commands::Doctor { .. } | Commands::Init => {}
+        Commands::CompleteProjects => pm.get_all().iter().for_each(|prj| println!("{}", prj.name)),
         Commands::UpdateSuggestions => {
             println!("Downloading latest versions of the suggestions.");
             agpm_suggestions::download_resources::<Dirs>().map_err(|e| {
@@ -0,0 +1,270 @@
+//! The machine readable output of the commands that take `--format json`.
+//!
+//! Every command writes a single json object, with the version of its schema
+//! and a list with an object for each project:
+//!
+//! ```json
+//! {"schema_version": 1, "projects": [...]}
+//! ```
+//!
+//! The objects of each command are the structures of this module. Fields may
+//! be added without changing the schema version, but they are never removed
+//! or changed without increasing it.
+
+use agpm_project::{Project, UpdatePolicy};
+use amisgitpm::{BatchReport, DiskUsage, Outcome, ProjectInfo};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the schema of the json output
+pub const SCHEMA_VERSION: usize = 1;
+
+/// The object that every command writes
+#[derive(Debug, Serialize)]
+pub struct Output<T> {
+    /// Always [`SCHEMA_VERSION`]
+    pub schema_version: usize,
+    /// One object for each project
+    pub projects: Vec<T>,
+}
+
+impl<T: Serialize> Output<T> {
+    /// Wrap the objects of the projects
+    pub fn new(projects: Vec<T>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            projects,
+        }
+    }
+    /// Write the output to the standard output
+    pub fn print(&self) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(std::io::stdout(), self)?;
+        println!();
+        Ok(())
+    }
+}
+
+/// A project, as written by `agpm list`
+#[derive(Debug, Serialize)]
+pub struct ListEntry {
+    /// The name of the project
+    pub name: String,
+    /// The directory of the project
+    pub dir: String,
+    /// The url it's cloned from
+    pub url: String,
+    /// The reference that is followed, like `refs/heads/main`
+    pub ref_string: String,
+    /// `Always`, `Ask` or `Never`
+    pub update_policy: UpdatePolicy,
+    /// The tags of the project
+    pub tags: Vec<String>,
+    /// The names of the projects it depends on
+    pub dependencies: Vec<String>,
+    /// The commit that was built, or null if it can't be found
+    pub commit: Option<String>,
+}
+
+impl ListEntry {
+    /// The entry of a project that was built at `commit`
+    pub fn new(prj: &Project, commit: Option<String>) -> Self {
+        Self {
+            name: prj.name.clone(),
+            dir: prj.dir.clone(),
+            url: prj.url.clone(),
+            ref_string: prj.ref_string.clone(),
+            update_policy: prj.update_policy,
+            tags: prj.tags.clone(),
+            dependencies: prj.dependencies.clone(),
+            commit,
+        }
+    }
+}
+
+/// Whether a project has new commits upstream, as written by `agpm outdated`
+#[derive(Debug, Serialize)]
+pub struct OutdatedEntry {
+    /// The name of the project
+    pub name: String,
+    /// Whether there are new commits. False when `error` is set
+    pub outdated: bool,
+    /// The commit that was built
+    pub installed: Option<String>,
+    /// The newest commit of the reference, if it's not the installed one
+    pub latest: Option<String>,
+    /// Why the project couldn't be checked, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The space that a project takes in the disk, as written by `agpm du`
+#[derive(Debug, Serialize)]
+pub struct DuEntry {
+    /// The name of the project
+    pub name: String,
+    /// The bytes of the sources
+    pub src: u64,
+    /// The bytes of the copy of the previous version
+    pub old: u64,
+    /// The bytes of both
+    pub total: u64,
+}
+
+impl DuEntry {
+    /// The entry of the project `name`
+    pub fn new(name: &str, usage: DiskUsage) -> Self {
+        Self {
+            name: name.to_string(),
+            src: usage.src,
+            old: usage.old,
+            total: usage.total(),
+        }
+    }
+}
+
+/// What happened to a project in `agpm update`
+#[derive(Debug, Serialize)]
+pub struct UpdateEntry {
+    /// The name of the project
+    pub name: String,
+    /// `updated`, `rebuilt`, `skipped` or `failed`. The projects that are
+    /// `rebuilt` were only built again, as a project they depend on changed
+    pub outcome: &'static str,
+    /// Why it was skipped or how it failed
+    pub message: Option<String>,
+}
+
+impl UpdateEntry {
+    /// The entries of all the projects of the report of an update
+    pub fn from_report<E: std::fmt::Display>(report: &BatchReport<E>) -> Vec<Self> {
+        report
+            .outcomes
+            .iter()
+            .map(|(name, outcome)| {
+                let (outcome, message) = match outcome {
+                    Outcome::Done if report.was_rebuilt(name) => ("rebuilt", None),
+                    Outcome::Done => ("updated", None),
+                    Outcome::Skipped(reason) => ("skipped", Some(reason.clone())),
+                    Outcome::Failed(e) => ("failed", Some(e.to_string())),
+                };
+                Self {
+                    name: name.clone(),
+                    outcome,
+                    message,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The state of a project, as written by `agpm info`
+#[derive(Debug, Serialize)]
+pub struct InfoEntry {
+    /// The name of the project
+    pub name: String,
+    /// The directory of its sources
+    pub src: String,
+    /// The url of its configuration
+    pub url: String,
+    /// The url of the remote of its repository
+    pub remote_url: Option<String>,
+    /// The reference of its configuration
+    pub ref_string: String,
+    /// The branch that the repository is at, or null when it's detached
+    pub branch: Option<String>,
+    /// The commit that the repository is at
+    pub commit: String,
+    /// The commit described from the closest tag
+    pub describe: Option<String>,
+    /// Whether the tracked files have uncommitted changes
+    pub dirty: bool,
+    /// When the repository was last fetched, in seconds since the unix epoch
+    pub last_fetch: Option<u64>,
+    /// The commits of the versions that can be restored, newest first
+    pub generations: Vec<String>,
+    /// The bytes of the sources
+    pub src_size: u64,
+    /// The bytes of the versions that can be restored
+    pub old_size: u64,
+}
+
+impl From<ProjectInfo> for InfoEntry {
+    fn from(info: ProjectInfo) -> Self {
+        Self {
+            name: info.name,
+            src: info.src.display().to_string(),
+            url: info.url,
+            remote_url: info.remote_url,
+            ref_string: info.ref_string,
+            branch: info.branch,
+            commit: info.commit,
+            describe: info.describe,
+            dirty: info.dirty,
+            last_fetch: info
+                .last_fetch
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_secs()),
+            generations: info.generations,
+            src_size: info.disk_usage.src,
+            old_size: info.disk_usage.old,
+        }
+    }
+}
+
+/// Write how long ago something happened for people, like `3 hours ago`
+pub fn ago(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |e| e.as_secs());
+    let (amount, unit) = match secs {
+        0..=59 => return "just now".into(),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    format!("{amount} {unit}{plural} ago")
+}
+
+/// Write an amount of bytes for people, like `1.5 MiB`
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Write a table for people with what happened to each project of a batch
+/// operation. `done` is the word for the projects with which it was done, like
+/// `updated`, and those that were only built again are `rebuilt`
+pub fn summary_table<E: std::fmt::Display>(report: &BatchReport<E>, done: &str) -> String {
+    let rows: Vec<(&str, &str, String)> = report
+        .outcomes
+        .iter()
+        .map(|(name, outcome)| match outcome {
+            Outcome::Done if report.was_rebuilt(name) => (name.as_str(), "rebuilt", String::new()),
+            Outcome::Done => (name.as_str(), done, String::new()),
+            Outcome::Skipped(reason) => (name.as_str(), "skipped", reason.clone()),
+            Outcome::Failed(e) => (name.as_str(), "failed", e.to_string()),
+        })
+        .collect();
+    let name_width = rows.iter().map(|e| e.0.len()).chain([7]).max().unwrap_or(7);
+    let result_width = rows.iter().map(|e| e.1.len()).chain([6]).max().unwrap_or(6);
+    let mut table = format!(
+        "{:name_width$}  {:result_width$}  Details\n",
+        "Project", "Result"
+    );
+    for (name, result, details) in rows {
+        let line = format!("{name:name_width$}  {result:result_width$}  {details}");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
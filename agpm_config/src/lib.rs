@@ -0,0 +1,92 @@
+#![warn(missing_docs)]
+#![doc = include_str!("../README.md")]
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The configurations that have been loaded, by the path of their file
+static LOADED: Mutex<Vec<(PathBuf, Arc<Config>)>> = Mutex::new(Vec::new());
+
+/// The content of a configuration file
+#[derive(Debug, Default)]
+pub struct Config {
+    file: Value,
+}
+
+impl Config {
+    /// Get the configuration of the file at `path`. The file is only read the
+    /// first time, and the following calls share that configuration. If the
+    /// file doesn't exist the configuration is empty
+    /// # Errors
+    /// - Unable to read the file
+    /// - The file isn't valid json
+    pub fn load(path: &Path) -> Result<Arc<Self>, ConfigError> {
+        let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, config)) = loaded.iter().find(|(file, _)| file == path) {
+            return Ok(config.clone());
+        }
+        let file = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Null,
+            Err(e) => Err(e)?,
+        };
+        let config = Arc::new(Self { file });
+        loaded.push((path.to_path_buf(), config.clone()));
+        Ok(config)
+    }
+    /// Read a part of the configuration, made of some of the fields of the
+    /// file. The fields that the file doesn't have take their default values
+    /// # Errors
+    /// - The fields of the file don't have the types of the fields of `T`
+    pub fn get<T: DeserializeOwned + Default>(&self) -> Result<T, ConfigError> {
+        match self.file {
+            Value::Null => Ok(T::default()),
+            _ => Ok(T::deserialize(&self.file)?),
+        }
+    }
+}
+
+/// An error while loading the configuration file
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// Couldn't read the file
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// The file has a bad format
+    #[error("Bad configuration file: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Deserialize, PartialEq)]
+    #[serde(default)]
+    struct Part {
+        generations: usize,
+        editor: Option<String>,
+    }
+
+    #[test]
+    fn loads_once() {
+        let path = std::env::temp_dir().join(format!("agpm_config_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"generations": 3, "other": true}"#).unwrap();
+        let config = Config::load(&path).unwrap();
+        let part: Part = config.get().unwrap();
+        assert_eq!(
+            part,
+            Part {
+                generations: 3,
+                editor: None
+            }
+        );
+        std::fs::write(&path, r#"{"generations": 5}"#).unwrap();
+        assert_eq!(Config::load(&path).unwrap().get::<Part>().unwrap(), part);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,40 +1,86 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+use agpm_config::{Config, ConfigError};
 use amisgitpm::Directories;
 use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use thiserror::Error;
+mod temp;
+pub use temp::TempDirs;
+
+/// The directories that are set in the `dirs` object of the configuration
+/// file, instead of the default ones. For example
+/// `{"dirs": {"src": "/mnt/big/agpm/src"}}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirsConfig {
+    /// Where the projects are stored
+    pub projects: Option<PathBuf>,
+    /// Where the projects are built
+    pub src: Option<PathBuf>,
+    /// Where the projects are downloaded and updated
+    pub git: Option<PathBuf>,
+    /// Where the previous versions of the projects are kept
+    pub old: Option<PathBuf>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    dirs: DirsConfig,
+}
 
 /// An implementor for the [`Directories`](amisgitpm::Directories) trait
 pub struct Dirs {
     p_dirs: ProjectDirs,
+    overrides: DirsConfig,
 }
 
 impl Directories for Dirs {
     type Error = DirError;
-    ///
     fn new() -> Result<Self, Self::Error> {
+        let p_dirs =
+            ProjectDirs::from("org", "amisoft", "agpm").ok_or(Self::Error::HomeNotFound)?;
+        let config = Config::load(&p_dirs.config_dir().join("config.json"))?;
         Ok(Self {
-            p_dirs: ProjectDirs::from("org", "amisoft", "agpm").ok_or(Self::Error::HomeNotFound)?,
+            p_dirs,
+            overrides: config.get::<ConfigFile>()?.dirs,
         })
     }
-    ///`~/.config/amisgitpm/projects` in Linux
+    ///`~/.config/amisgitpm/projects` in Linux, unless it's set in the configuration
     fn projects_db(&self) -> PathBuf {
-        self.p_dirs.config_dir().join("projects")
+        self.overrides
+            .projects
+            .clone()
+            .unwrap_or_else(|| self.p_dirs.config_dir().join("projects"))
     }
-    /// `~/.local/share/amisgitpm/src` in Linux
+    /// `~/.local/share/amisgitpm/src` in Linux, unless it's set in the configuration
     fn src(&self) -> PathBuf {
-        self.p_dirs.data_local_dir().join("src")
+        self.overrides
+            .src
+            .clone()
+            .unwrap_or_else(|| self.p_dirs.data_local_dir().join("src"))
     }
 
-    /// `~/.local/share/amisgitpm/git_ops` in Linux
+    /// `~/.local/share/amisgitpm/git_ops` in Linux, unless it's set in the configuration
     fn git(&self) -> PathBuf {
-        self.p_dirs.data_local_dir().join("git_ops")
+        self.overrides
+            .git
+            .clone()
+            .unwrap_or_else(|| self.p_dirs.data_local_dir().join("git_ops"))
     }
-    ///`~/.local/share/amisgitpm/old` in Linux
+    ///`~/.local/share/amisgitpm/old` in Linux, unless it's set in the configuration
     fn old(&self) -> PathBuf {
-        self.p_dirs.data_local_dir().join("old")
+        self.overrides
+            .old
+            .clone()
+            .unwrap_or_else(|| self.p_dirs.data_local_dir().join("old"))
+    }
+    ///`~/.config/amisgitpm/config.json` in Linux
+    fn config(&self) -> PathBuf {
+        self.p_dirs.config_dir().join("config.json")
     }
 }
 
@@ -60,4 +106,10 @@ pub enum DirError {
     /// An error when no project-based default directories can be found
     #[error("Couldn't find a $HOME or equivalent in your platform")]
     HomeNotFound,
+    /// An error while creating the temporary directories
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// The configuration file can't be read
+    #[error(transparent)]
+    Config(#[from] ConfigError),
 }
@@ -0,0 +1,77 @@
+use crate::DirError;
+use amisgitpm::Directories;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How many `TempDirs` have been created by this process, to give each one its
+/// own directory
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// An implementor for the [`Directories`](amisgitpm::Directories) trait that
+/// places everything in a new temporary directory, which is removed with all
+/// its contents when the `TempDirs` is dropped
+///
+/// Every call to `new` gives a different directory, so project managers built
+/// with it don't interfere with each other and can be used in parallel. Stores
+/// that open their own `Directories`, like `agpm_store::Store`, would get a
+/// different one, so they should be paired with `agpm_store::MemoryStore`.
+///
+/// The directories follow the same layout as the real ones:
+///
+/// - `{root}/config/projects`, for the projects db
+/// - `{root}/config/config.json`, for the configuration
+/// - `{root}/cache/src`, `{root}/cache/git` and `{root}/cache/old`
+pub struct TempDirs {
+    root: PathBuf,
+}
+
+impl Directories for TempDirs {
+    type Error = DirError;
+    fn new() -> Result<Self, Self::Error> {
+        let root = std::env::temp_dir().join(format!(
+            "agpm-{}-{}",
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed)
+        ));
+        if root.exists() {
+            // Left behind by a process that had the same id
+            std::fs::remove_dir_all(&root)?;
+        }
+        std::fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+    fn projects_db(&self) -> PathBuf {
+        self.root.join("config").join("projects")
+    }
+    fn src(&self) -> PathBuf {
+        self.root.join("cache").join("src")
+    }
+    fn git(&self) -> PathBuf {
+        self.root.join("cache").join("git")
+    }
+    fn old(&self) -> PathBuf {
+        self.root.join("cache").join("old")
+    }
+}
+
+impl TempDirs {
+    /// The temporary directory that holds all the others
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for TempDirs {
+    fn drop(&mut self) {
+        // There is nowhere to report the error, and the OS cleans up the
+        // temporary directory eventually
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[cfg(feature = "suggestions")]
+impl agpm_suggestions::SuggestionsDirs for TempDirs {
+    fn suggestions(&self) -> PathBuf {
+        self.root.join("config").join("suggestions")
+    }
+}
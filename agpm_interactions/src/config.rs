@@ -0,0 +1,16 @@
+use agpm_project::UpdatePolicy;
+use serde::{Deserialize, Serialize};
+
+/// The part of the configuration file that concerns the
+/// [`Interactor`](crate::Interactor). Fields that are not present take their
+/// default values
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InteractConfig {
+    /// The update policy of new projects when it isn't asked for, and the one
+    /// that is offered first when it is
+    pub update_policy: UpdatePolicy,
+    /// The program used to edit scripts and projects. By default the one in
+    /// the `VISUAL` or `EDITOR` environment variables
+    pub editor: Option<String>,
+}
@@ -1,5 +1,7 @@
+use agpm_config::ConfigError;
 #[cfg(feature = "suggestions")]
 use agpm_suggestions::SuggestionsError;
+use amisgitpm::Hint;
 use thiserror::Error;
 
 #[non_exhaustive]
@@ -19,4 +21,42 @@ pub enum InteractError {
     #[error(transparent)]
     /// A failure while trying to provide suggestions
     Suggestion(#[from] SuggestionsError),
+    /// A name or directory that was given beforehand is used by another project
+    #[error("A project already uses the name or directory {0:?}")]
+    Taken(String),
+    /// The suggestion that was accepted doesn't exist. Holds its number and
+    /// the number of suggestions
+    #[error("There is no suggestion number {0}, there are {1}")]
+    NoSuggestion(usize, usize),
+    /// The directories can't be found
+    #[error("{0}")]
+    Dirs(String),
+    /// The configuration file can't be read
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+}
+
+impl Hint for InteractError {
+    fn hint(&self) -> Option<String> {
+        Some(match self {
+            Self::Git(_) => "Check that the repository of the project has branches".into(),
+            Self::IO(_) => "Check that the terminal and the editor can be used".into(),
+            Self::Serde(_) => "Edit the project again, keeping it valid json".into(),
+            #[cfg(feature = "suggestions")]
+            Self::Suggestion(_) => "Get the suggestions again with `agpm update-suggestions`".into(),
+            Self::Taken(taken) => {
+                format!("Give the project another name or directory than {taken:?}")
+            }
+            Self::NoSuggestion(_, 0) => {
+                "There are no suggestions for the project, give its scripts instead".into()
+            }
+            Self::NoSuggestion(_, count) => format!(
+                "Accept one of the {count} suggestions, or install without accepting one to see them"
+            ),
+            Self::Dirs(_) => "Check that `$HOME` is set".into(),
+            Self::Config(_) => {
+                "Fix the configuration file, or remove it to use the defaults".into()
+            }
+        })
+    }
 }
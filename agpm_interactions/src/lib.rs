@@ -1,6 +1,7 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+use agpm_config::Config;
 use agpm_pm::Interactions;
 use agpm_project::{Project, UpdatePolicy};
 #[cfg(feature = "suggestions")]
@@ -11,9 +12,13 @@ use dialoguer::{Confirm, Editor, Input, MultiSelect, Select};
 use git2::{BranchType, Repository};
 use prettytable as pt;
 use prettytable::row;
-use std::{marker::PhantomData, path::Path};
+use std::{io::IsTerminal, marker::PhantomData, path::Path};
 mod error;
 pub use error::InteractError;
+mod presets;
+pub use presets::InstallPresets;
+mod config;
+pub use config::InteractConfig;
 
 /// This struct implements the [`agpm_pm::Interactions`] trait. To that purpose
 pub struct Interactor<
@@ -21,6 +26,8 @@ pub struct Interactor<
     #[cfg(feature = "suggestions")] T: Directories + SuggestionsDirs,
 > {
     t: Term,
+    interactive: bool,
+    config: InteractConfig,
     dirs: PhantomData<T>,
 }
 
@@ -29,7 +36,7 @@ impl<
         #[cfg(feature = "suggestions")] T: Directories + SuggestionsDirs,
     > Interactor<T>
 {
-    fn get_sugg(&self, sug: &Vec<Vec<String>>, info: &str) -> Result<Vec<String>, InteractError> {
+    fn get_sugg(&self, sug: &[Vec<String>], info: &str) -> Result<Vec<String>, InteractError> {
         let mut edit_string = String::new();
         self.t.clear_screen()?;
         if !sug.is_empty() {
@@ -69,7 +76,7 @@ when you are done press {}", style("all").bold(), style("space").bold(), style("
                 }
             }
         };
-        if let Some(final_install) = Editor::new().edit(&edit_string)? {
+        if let Some(final_install) = self.editor().edit(&edit_string)? {
             Ok(final_install.split('\n').map(|e| e.to_string()).collect())
         } else {
             Ok(vec![])
@@ -104,23 +111,115 @@ when you are done press {}", style("all").bold(), style("space").bold(), style("
         }
     }
 
+    fn editor(&self) -> Editor {
+        let mut editor = Editor::new();
+        if let Some(program) = &self.config.editor {
+            editor.executable(program);
+        }
+        editor
+    }
+
     fn get_updates(&self) -> Result<UpdatePolicy, InteractError> {
         self.t.clear_screen()?;
         println!("Now we are trying to get an update policy");
         let update_array = vec![UpdatePolicy::Ask, UpdatePolicy::Always, UpdatePolicy::Never];
-        let idx = Select::new().items(&update_array).interact()?;
+        let default = update_array
+            .iter()
+            .position(|e| *e == self.config.update_policy)
+            .unwrap_or_default();
+        let idx = Select::new()
+            .items(&update_array)
+            .default(default)
+            .interact()?;
         Ok(update_array[idx])
     }
 
+    fn get_dependencies(
+        &self,
+        store: &impl ProjectStore<Project>,
+    ) -> Result<Vec<String>, InteractError> {
+        if store.is_empty() {
+            return Ok(vec![]);
+        }
+        self.t.clear_screen()?;
+        let names: Vec<&str> = store.iter().map(|e| e.name.as_str()).collect();
+        let choices = MultiSelect::new()
+            .items(&names)
+            .with_prompt(format!(
+                "Please select the projects this one {} on, press {} next to all that apply
+when you are done press {}",
+                style("depends").bold(),
+                style("space").bold(),
+                style("enter").bold()
+            ))
+            .report(false)
+            .interact()?;
+        Ok(choices.into_iter().map(|i| names[i].to_string()).collect())
+    }
+
+    fn get_tags(&self, store: &impl ProjectStore<Project>) -> Result<Vec<String>, InteractError> {
+        self.t.clear_screen()?;
+        let mut known: Vec<&str> = store
+            .iter()
+            .flat_map(|e| e.tags.iter().map(|e| e.as_str()))
+            .collect();
+        known.sort_unstable();
+        known.dedup();
+        let tags: String = Input::new()
+            .with_prompt(format!(
+                "Please write the {} of the project separated by commas, or leave it empty.
+The tags already in use are: {}\n",
+                style("tags").bold(),
+                known.join(", ")
+            ))
+            .allow_empty(true)
+            .interact_text()?;
+        Ok(tags
+            .split(',')
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect())
+    }
+
+    fn get_requirements(&self, sugg: &[String]) -> Result<Vec<String>, InteractError> {
+        if sugg.is_empty() {
+            return Ok(vec![]);
+        }
+        self.t.clear_screen()?;
+        let choices = MultiSelect::new()
+            .items(sugg)
+            .defaults(&vec![true; sugg.len()])
+            .with_prompt(format!(
+                "These programs are needed to build the project, they will be {} before
+building. Press {} to toggle them and {} when you are done",
+                style("checked").bold(),
+                style("space").bold(),
+                style("enter").bold()
+            ))
+            .report(false)
+            .interact()?;
+        Ok(choices.into_iter().map(|i| sugg[i].clone()).collect())
+    }
+
     #[allow(unused_variables)]
     #[allow(unreachable_code)]
-    fn provide_suggestions(wher: &Path) -> (Vec<Vec<String>>, Vec<Vec<String>>) {
+    fn provide_suggestions(wher: &Path) -> (Vec<Vec<String>>, Vec<Vec<String>>, Vec<String>) {
         #[cfg(feature = "suggestions")]
         {
             return agpm_suggestions::get_suggestions::<T>(wher).unwrap();
         }
         // This code is reachable when the feature suggestions is enabled
-        (vec![], vec![])
+        (vec![], vec![], vec![])
+    }
+}
+
+/// Check that a name or directory that wasn't asked for is free, as there may
+/// be nobody to ask for another
+fn check_free(value: String, free: impl Fn(&str) -> bool) -> Result<String, InteractError> {
+    if free(&value) {
+        Ok(value)
+    } else {
+        Err(InteractError::Taken(value))
     }
 }
 
@@ -131,17 +230,36 @@ impl<
     > Interactions<Project, ST> for Interactor<T>
 {
     type Error = InteractError;
+    type Presets = InstallPresets;
     fn new() -> Result<Self, Self::Error> {
+        let dirs = T::new().map_err(|e| InteractError::Dirs(e.to_string()))?;
         Ok(Self {
             t: Term::stdout(),
-            dirs: PhantomData::default(),
+            interactive: std::io::stdin().is_terminal(),
+            config: Config::load(&dirs.config())?.get()?,
+            dirs: PhantomData,
         })
     }
     fn repo_to_checkout_branch(
         &self,
         mut prj: Project,
         repo: &Repository,
+        presets: &InstallPresets,
     ) -> Result<Project, Self::Error> {
+        if let Some(ref_string) = &presets.ref_string {
+            prj.ref_string = if ref_string.starts_with("refs/") {
+                ref_string.clone()
+            } else {
+                format!("refs/heads/{ref_string}")
+            };
+            return Ok(prj);
+        }
+        if !self.interactive {
+            if let Some(head) = repo.head()?.name() {
+                prj.ref_string = head.to_string();
+                return Ok(prj);
+            }
+        }
         let branch_arr: Vec<String> = repo
             .branches(Some(BranchType::Local))?
             .filter_map(|br| br.ok())
@@ -161,57 +279,98 @@ impl<
         prj_stub: &Project,
         store: &ST,
         wher: &Path,
+        presets: &InstallPresets,
     ) -> Result<Project, Self::Error> {
-        let (ins, unins) = Self::provide_suggestions(wher);
-        let sugg_name = prj_stub
-            .url
-            .split('/')
-            .last()
-            .map_or(String::new(), |potential_dir| {
-                potential_dir
-                    .to_string()
-                    .rsplit_once('.')
-                    .map_or(potential_dir.to_string(), |(dir, _)| dir.to_string())
-            });
-        let name = self.get_name_or_dir(
-            &sugg_name,
-            (
-                "What's the name of the project going to be?",
-                "Please provide a name for the project",
-                "A project already uses that name, please suggest another",
-            ),
-            |a| !store.check_name_free(a),
-        )?;
-        let dir = self.get_name_or_dir(
-            &name,
-            (
-                &format!(
-                    "What's the {} name of the project going to be?
+        let (ins, unins, reqs) = Self::provide_suggestions(wher);
+        let sugg_name =
+            prj_stub
+                .url
+                .split('/')
+                .next_back()
+                .map_or(String::new(), |potential_dir| {
+                    potential_dir
+                        .to_string()
+                        .rsplit_once('.')
+                        .map_or(potential_dir.to_string(), |(dir, _)| dir.to_string())
+                });
+        let name = match presets.name.clone() {
+            Some(name) => check_free(name, |a| store.check_name_free(a))?,
+            None if self.interactive => self.get_name_or_dir(
+                &sugg_name,
+                (
+                    "What's the name of the project going to be?",
+                    "Please provide a name for the project",
+                    "A project already uses that name, please suggest another",
+                ),
+                |a| !store.check_name_free(a),
+            )?,
+            None => check_free(sugg_name, |a| store.check_name_free(a))?,
+        };
+        let dir = match presets.dir.clone() {
+            Some(dir) => check_free(dir, |a| store.check_dir_free(a))?,
+            None if self.interactive => self.get_name_or_dir(
+                &name,
+                (
+                    &format!(
+                        "What's the {} name of the project going to be?
 The directory is a name for a folder",
-                    style("directory").bold()
+                        style("directory").bold()
+                    ),
+                    "Please provide a directory name",
+                    "A project already uses that directory, please suggest another",
                 ),
-                "Please provide a directory name",
-                "A project already uses that directory, please suggest another",
-            ),
-            |a| !store.check_dir_free(a),
-        )?;
-        let update_policy = self.get_updates()?;
-        let install_script = self.get_sugg(
-            &ins,
-            &format!(
-                "Now we have to establish how to build and install the program.
+                |a| !store.check_dir_free(a),
+            )?,
+            None => check_free(name.clone(), |a| store.check_dir_free(a))?,
+        };
+        let update_policy = match presets.update_policy {
+            Some(policy) => policy,
+            None if self.interactive => self.get_updates()?,
+            None => self.config.update_policy,
+        };
+        let dependencies = match &presets.dependencies {
+            Some(dependencies) => dependencies.clone(),
+            None if self.interactive => self.get_dependencies(store)?,
+            None => vec![],
+        };
+        let tags = match &presets.tags {
+            Some(tags) => tags.clone(),
+            None if self.interactive => self.get_tags(store)?,
+            None => vec![],
+        };
+        let accepted = presets
+            .accept_suggestion
+            .map(|n| {
+                n.checked_sub(1)
+                    .and_then(|i| ins.get(i))
+                    .cloned()
+                    .ok_or(InteractError::NoSuggestion(n, ins.len()))
+            })
+            .transpose()?;
+        let install_script = match (&presets.install_script, accepted) {
+            (Some(script), _) => script.clone(),
+            (None, Some(script)) => script,
+            (None, None) if !self.interactive => vec![],
+            (None, None) => self.get_sugg(
+                &ins,
+                &format!(
+                    "Now we have to establish how to build and install the program.
 Please keep two things in mind:
 1) The script will be run from the {} of the project.
 2) All the lines in your script will be {}. If you want to detach some
 commands you might want to do something like this `command-to-detach & cd .`",
-                style("topmost directory").bold(),
-                style("joined by `&&`").bold()
-            ),
-        )?;
-        let uninstall_script = self.get_sugg(
-            &unins,
-            &format!(
-                "Now we have to establish how to uninstall the program.
+                    style("topmost directory").bold(),
+                    style("joined by `&&`").bold()
+                ),
+            )?,
+        };
+        let uninstall_script = match &presets.uninstall_script {
+            Some(script) => script.clone(),
+            None if !self.interactive => vec![],
+            None => self.get_sugg(
+                &unins,
+                &format!(
+                    "Now we have to establish how to uninstall the program.
 You might want to trace:
 - Different executables/binaries
 - Cache that the program generates
@@ -220,11 +379,19 @@ Please keep two things in mind:
 1) The script will be run from the {} of the project.
 2) All the lines in your script will be {}. If you want to detach some
 commands you might want to do something like this `command-to-detach & cd .`",
-                style("topmost directory").bold(),
-                style("joined by `&&`").bold()
-            ),
-        )?;
-        self.t.clear_screen()?;
+                    style("topmost directory").bold(),
+                    style("joined by `&&`").bold()
+                ),
+            )?,
+        };
+        let requirements = match &presets.requirements {
+            Some(requirements) => requirements.clone(),
+            None if self.interactive => self.get_requirements(&reqs)?,
+            None => reqs,
+        };
+        if self.interactive {
+            self.t.clear_screen()?;
+        }
         println!("Setup is finished, starting to build");
         Ok(Project {
             name,
@@ -234,11 +401,15 @@ commands you might want to do something like this `command-to-detach & cd .`",
             update_policy,
             install_script,
             uninstall_script,
+            dependencies,
+            requirements,
+            tags,
+            ..Default::default()
         })
     }
 
     fn edit(&self, prj: Project) -> Result<Project, Self::Error> {
-        if let Some(e) = Editor::new().edit(&serde_json::to_string_pretty(&prj)?)? {
+        if let Some(e) = self.editor().edit(&serde_json::to_string_pretty(&prj)?)? {
             Ok(serde_json::from_str::<Project>(&e)?)
         } else {
             Ok(prj)
@@ -251,13 +422,21 @@ commands you might want to do something like this `command-to-detach & cd .`",
             "Directory name",
             "Project URL",
             "Reference",
-            "Update policy"
+            "Update policy",
+            "Tags"
         ]);
         if store.is_empty() {
             return Ok(());
         }
         store.iter().for_each(|e| {
-            show_table.add_row(row![e.name, e.dir, e.url, e.ref_string, e.update_policy]);
+            show_table.add_row(row![
+                e.name,
+                e.dir,
+                e.url,
+                e.ref_string,
+                e.update_policy,
+                e.tags.join(", ")
+            ]);
         });
         println!("{show_table}");
         Ok(())
@@ -276,7 +455,7 @@ commands you might want to do something like this `command-to-detach & cd .`",
             UpdatePolicy::Never => false,
         }
     }
-    fn url_to_download(&self, url: &str) -> Result<Project, Self::Error> {
+    fn url_to_download(&self, url: &str, presets: &InstallPresets) -> Result<Project, Self::Error> {
         let url = if url.ends_with('/') {
             let (a, _) = url.rsplit_once('/').unwrap();
             a
@@ -285,7 +464,7 @@ commands you might want to do something like this `command-to-detach & cd .`",
         };
         let sugg = url
             .split('/')
-            .last()
+            .next_back()
             .map_or("temp".into(), |potential_dir| {
                 potential_dir
                     .to_string()
@@ -295,7 +474,7 @@ commands you might want to do something like this `command-to-detach & cd .`",
 
         Ok(Project {
             url: url.to_string(),
-            dir: sugg,
+            dir: presets.dir.clone().unwrap_or(sugg),
             ..Default::default()
         })
     }
@@ -0,0 +1,33 @@
+use agpm_project::UpdatePolicy;
+
+/// Values of a new project that are known beforehand, for example from the
+/// command line. The [`Interactor`](crate::Interactor) doesn't ask for the
+/// values that are set, and when the standard input isn't a terminal it asks
+/// for nothing, using the defaults for the values that aren't set.
+#[derive(Debug, Clone, Default)]
+pub struct InstallPresets {
+    /// The name of the project. By default that of the repository
+    pub name: Option<String>,
+    /// The directory of the project. By default its name
+    pub dir: Option<String>,
+    /// The reference to follow, like `refs/heads/main` or `main`. By default
+    /// the branch that the repository is at after cloning it
+    pub ref_string: Option<String>,
+    /// By default the `update_policy` of the configuration file, see
+    /// [`InteractConfig`](crate::InteractConfig)
+    pub update_policy: Option<UpdatePolicy>,
+    /// The steps to build the project. By default those of the accepted
+    /// suggestion, or none
+    pub install_script: Option<Vec<String>>,
+    /// The steps to uninstall the project. By default none
+    pub uninstall_script: Option<Vec<String>>,
+    /// Use the install suggestion with this number, counting from 1 in the
+    /// order they are shown, as the install script
+    pub accept_suggestion: Option<usize>,
+    /// The projects it depends on. By default none
+    pub dependencies: Option<Vec<String>>,
+    /// The tags of the project. By default none
+    pub tags: Option<Vec<String>>,
+    /// The programs needed to build it. By default those suggested
+    pub requirements: Option<Vec<String>>,
+}
@@ -0,0 +1,96 @@
+use agpm_config::{Config, ConfigError};
+use amisgitpm::Hook;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Scripts that are run for every project, before the project's own hooks.
+/// Each of them is joined with && before execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GlobalHooks {
+    /// Run before building a project for the first time
+    pub pre_install: Vec<String>,
+    /// Run after building a project for the first time
+    pub post_install: Vec<String>,
+    /// Run before building the new version of a project
+    pub pre_update: Vec<String>,
+    /// Run after building the new version of a project
+    pub post_update: Vec<String>,
+    /// Run before uninstalling a project
+    pub pre_uninstall: Vec<String>,
+    /// Run after uninstalling a project
+    pub post_uninstall: Vec<String>,
+}
+
+impl GlobalHooks {
+    /// Get the script for a hook
+    pub fn get(&self, hook: Hook) -> &[String] {
+        match hook {
+            Hook::PreInstall => &self.pre_install,
+            Hook::PostInstall => &self.post_install,
+            Hook::PreUpdate => &self.pre_update,
+            Hook::PostUpdate => &self.post_update,
+            Hook::PreUninstall => &self.pre_uninstall,
+            Hook::PostUninstall => &self.post_uninstall,
+        }
+    }
+}
+
+/// How many projects are handled at once when updating several of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Parallelism {
+    /// How many projects are fetched at once
+    pub fetch: usize,
+    /// How many projects are built at once
+    pub build: usize,
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Self { fetch: 4, build: 1 }
+    }
+}
+
+/// The part of the configuration file that concerns the `PrjManager`. Fields
+/// that are not present take their default values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PMConfig {
+    /// The hooks that apply to every project
+    pub hooks: GlobalHooks,
+    /// How many previous versions of each project are kept, to go back to
+    /// them with `restore`. By default one
+    pub generations: usize,
+    /// How many projects are fetched and built at once
+    pub parallelism: Parallelism,
+    /// The seconds after which a script is stopped and fails. By default
+    /// scripts can take as long as they need
+    pub build_timeout: Option<u64>,
+    /// Where the projects should install themselves. Scripts get it as the
+    /// `PREFIX` environment variable
+    pub prefix: Option<PathBuf>,
+}
+
+impl Default for PMConfig {
+    fn default() -> Self {
+        Self {
+            hooks: GlobalHooks::default(),
+            generations: 1,
+            parallelism: Parallelism::default(),
+            build_timeout: None,
+            prefix: None,
+        }
+    }
+}
+
+impl PMConfig {
+    /// Read the configuration from a json file, see [`agpm_config::Config`].
+    /// If the file doesn't exist the default configuration is used
+    /// # Errors
+    /// - Unable to read the file
+    /// - The file isn't a valid configuration
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Config::load(path)?.get()
+    }
+}
@@ -1,25 +1,34 @@
-use amisgitpm::CommonPMErrors;
+use crate::ConfigError;
+use amisgitpm::{CommonPMErrors, ErrorClass, GitError, Hint, PathError};
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
 /// The error type for the `PrjManager` implementations
 pub enum PMError<D, ST, I> {
-    /// Errors with git operations
+    /// Errors with git operations, holding the project they were done with
     #[error(transparent)]
-    Git(#[from] git2::Error),
+    Git(#[from] GitError),
     /// The error while starting an install/uninstall command
     #[error(transparent)]
     Spawn(#[from] subprocess::PopenError),
-    /// An error while recursively copying directories.
-    #[error(transparent)]
-    FileExt(#[from] fs_extra::error::Error),
+    /// An error while recursively copying directories. Holds the directory
+    /// that was copied and where to
+    #[error("Unable to copy {0:?} to {1:?}: {2}")]
+    FileExt(PathBuf, PathBuf, fs_extra::error::Error),
     /// An error while opening files, w
     #[error(transparent)]
     IO(#[from] std::io::Error),
+    /// An error with a file or a directory, holding its path
+    #[error(transparent)]
+    Path(#[from] PathError),
     /// One of the more common errors
     #[error(transparent)]
     Common(#[from] CommonPMErrors),
+    /// An error while reading the configuration file
+    #[error(transparent)]
+    Config(#[from] ConfigError),
     /// An error that has arisen from an interaction
     #[error(transparent)]
     Interact(I),
@@ -29,7 +38,66 @@ pub enum PMError<D, ST, I> {
     /// An error from the directories
     #[error(transparent)]
     Dirs(D),
-    /// An error while executing an install/uninstall command
-    #[error("Failed to run script succesfully")]
-    Exec,
+    /// An error while executing an install/uninstall command. Holds the
+    /// name of the project and the script
+    #[error("Failed to run the script `{1}` of {0}")]
+    Exec(String, String),
+    /// A script took longer than the `build_timeout` of the configuration.
+    /// Holds the name of the project and the timeout in seconds
+    #[error("The script of {0} took longer than {1} seconds and was stopped")]
+    Timeout(String, u64),
+}
+
+impl<D, ST: Hint, I: Hint> PMError<D, ST, I> {
+    /// What can be done to solve the error, if anything is known. It's the
+    /// same for every front end of the manager
+    pub fn hint(&self) -> Option<String> {
+        Some(match self {
+            Self::Git(e) => e.hint(),
+            Self::Spawn(_) => {
+                "The program couldn't be started, check that it's installed and in the PATH".into()
+            }
+            Self::FileExt(from, to, _) => format!(
+                "Check the permissions of {from:?} and {to:?}, then remove what was left \
+                 behind with `agpm doctor --fix`"
+            ),
+            Self::IO(_) => "Check the permissions of the directories of agpm, then find what \
+                            was left behind with `agpm doctor`"
+                .into(),
+            Self::Path(e) => format!("{}. Find what was left behind with `agpm doctor`", e.hint()),
+            Self::Common(e) => e.hint(),
+            Self::Config(_) => {
+                "Fix the configuration file, or remove it to use the defaults".into()
+            }
+            Self::Store(e) => return e.hint(),
+            Self::Dirs(_) => {
+                "Check that `$HOME` is set, that the `dirs` of the configuration file are right, \
+                 and that the directories of agpm can be created"
+                    .into()
+            }
+            Self::Exec(prj, _) => format!(
+                "Edit the scripts of {prj} with `agpm edit {prj}`, and try them by hand \
+                 with `agpm exec {prj}`"
+            ),
+            Self::Timeout(prj, _) => format!(
+                "Raise `build_timeout` in the configuration file, or fix the scripts of \
+                 {prj} with `agpm edit {prj}`"
+            ),
+            Self::Interact(e) => return e.hint(),
+        })
+    }
+}
+
+impl<D, ST, I> PMError<D, ST, I> {
+    /// The class of the error, that gives the exit code of the command
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::Git(_) => ErrorClass::Git,
+            Self::Spawn(_) | Self::Exec(..) | Self::Timeout(..) => ErrorClass::Script,
+            Self::FileExt(..) | Self::IO(_) | Self::Path(_) => ErrorClass::Filesystem,
+            Self::Common(e) => e.class(),
+            Self::Config(_) | Self::Store(_) | Self::Dirs(_) => ErrorClass::Store,
+            Self::Interact(_) => ErrorClass::Other,
+        }
+    }
 }
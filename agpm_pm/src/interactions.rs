@@ -7,14 +7,28 @@ use std::path::Path;
 pub trait Interactions<P: ProjectIface, PS: ProjectStore<P>>: Sized {
     /// The error that interactions will return
     type Error: std::error::Error;
+    /// Values of a new project that are known beforehand, so that they aren't
+    /// asked for while installing it
+    type Presets: Default;
     /// Create an Interactions implementor
     fn new() -> Result<Self, Self::Error>;
     /// Go from an url to a downloadable project -> A project with `url`, and `dir`
-    fn url_to_download(&self, url: &str) -> Result<P, Self::Error>;
+    fn url_to_download(&self, url: &str, presets: &Self::Presets) -> Result<P, Self::Error>;
     /// Go from a git repo and obtain a valid reference string for the branch to be used
-    fn repo_to_checkout_branch(&self, prj: P, repo: &Repository) -> Result<P, Self::Error>;
+    fn repo_to_checkout_branch(
+        &self,
+        prj: P,
+        repo: &Repository,
+        presets: &Self::Presets,
+    ) -> Result<P, Self::Error>;
     /// Complete a project that starts with a `url`, a `ref_string` and a name
-    fn create_project(&self, prj_stub: &P, store: &PS, wher: &Path) -> Result<P, Self::Error>;
+    fn create_project(
+        &self,
+        prj_stub: &P,
+        store: &PS,
+        wher: &Path,
+        presets: &Self::Presets,
+    ) -> Result<P, Self::Error>;
     /// Interactively edit a project
     fn edit(&self, prj: P) -> Result<P, Self::Error>;
     /// List all projects
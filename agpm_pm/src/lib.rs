@@ -2,10 +2,15 @@
 #![doc = include_str!("../README.md")]
 
 use amisgitpm::{
-    CommonPMErrors, Directories, PMInteractive, PMOperations, PMProgrammatic, ProjectIface,
-    ProjectStore,
+    BatchReport, CommonPMErrors, Directories, Finding, Hook, PMInteractive, PMOperations,
+    PMProgrammatic, PathContext, ProjectIface, ProjectStore,
 };
+use std::ffi::OsString;
 use std::marker::PhantomData;
+use subprocess::{Exec, ExitStatus};
+mod config;
+pub use agpm_config::ConfigError;
+pub use config::{GlobalHooks, PMConfig, Parallelism};
 mod error;
 pub use error::PMError;
 mod interactions;
@@ -21,6 +26,9 @@ pub struct PrjManager<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: I
 {
     dirs: D,
     store: PS,
+    config: PMConfig,
+    /// Whether the output of the scripts goes to stderr instead of stdout
+    scripts_to_stderr: bool,
     inter_data: PhantomData<I>,
     p_data: PhantomData<P>,
 }
@@ -29,28 +37,15 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
     for PrjManager<P, D, PS, I>
 {
 }
+// Projects are updated in parallel, so the manager is shared by several threads
 impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS>> PMInteractive
     for PrjManager<P, D, PS, I>
+where
+    Self: Sync,
+    PMError<D::Error, PS::Error, I::Error>: Send,
 {
     fn i_install<T: AsRef<str>>(&mut self, url: T) -> Result<(), Self::Error> {
-        let inter = I::new().map_err(Self::map_inter_error)?;
-        let prj_stub = inter
-            .url_to_download(url.as_ref())
-            .map_err(Self::map_inter_error)?;
-        let (repo, git_dir) = self.download(&prj_stub)?;
-        let prj_stub = inter
-            .repo_to_checkout_branch(prj_stub, &repo)
-            .map_err(Self::map_inter_error)?;
-        self.switch_branch(&prj_stub, &repo)?;
-        let project = inter
-            .create_project(&prj_stub, self.get_store(), &git_dir)
-            .map_err(Self::map_inter_error)?;
-        self.get_mut_store()
-            .add(project.clone())
-            .map_err(Self::map_store_error)?;
-        self.mv(&project, &git_dir)?;
-        self.build(&project)?;
-        Ok(())
+        self.i_install_with(url, I::Presets::default(), &[])
     }
     fn i_list<T: AsRef<str>, Q: AsRef<[T]>>(&self, prj_names: Q) -> Result<(), Self::Error> {
         let inter = I::new().map_err(Self::map_inter_error)?;
@@ -63,7 +58,7 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
                 let project = self
                     .get_store()
                     .get_ref(prj_name.as_ref())
-                    .ok_or(CommonPMErrors::NonExisting)?;
+                    .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
                 inter.list_one(project).map_err(Self::map_inter_error)?;
                 Ok::<_, Self::Error>(())
             })?;
@@ -73,40 +68,42 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
     fn i_edit<T: AsRef<str>>(&mut self, project: T) -> Result<(), Self::Error> {
         let inter = I::new().map_err(Self::map_inter_error)?;
         if let Some(element) = self.get_store().get_clone(project.as_ref()) {
-            let old_name = element.get_name().to_owned();
+            let old = element.clone();
             let prj = inter.edit(element).map_err(Self::map_inter_error)?;
-            self.edit(&old_name, prj)?;
+            self.edit(old.get_name(), prj.clone())?;
+            if old.get_url() != prj.get_url() || old.get_ref_string() != prj.get_ref_string() {
+                self.rebuild(prj.get_name())?;
+            }
         }
         Ok(())
     }
-    fn i_update<T: AsRef<str>, Q: AsRef<[T]>>(&self, prj_names: Q) -> Result<(), Self::Error> {
+    fn i_update<T: AsRef<str>, Q: AsRef<[T]>>(
+        &self,
+        prj_names: Q,
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
         let inter = I::new().map_err(Self::map_inter_error)?;
-        if prj_names.as_ref().is_empty() {
+        let prjs: Vec<&str> = if prj_names.as_ref().is_empty() {
             self.get_store()
                 .iter()
                 .filter(|e| inter.update_confirm(e))
-                .try_for_each(|e| self.update(e.get_name()))?;
+                .map(|e| e.get_name())
+                .collect()
         } else {
-            for project in prj_names.as_ref() {
-                self.update(project.as_ref())?;
-            }
-        }
-        Ok(())
+            prj_names.as_ref().iter().map(|e| e.as_ref()).collect()
+        };
+        self.update_batch(&prjs)
     }
-    fn i_restore<T: AsRef<str>, Q: AsRef<[T]>>(self, prj_names: Q) -> Result<(), Self::Error> {
-        for prj in prj_names.as_ref() {
-            self.restore(prj)?;
-        }
-        Ok(())
+    fn i_restore<T: AsRef<str>, Q: AsRef<[T]>>(
+        self,
+        prj_names: Q,
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        self.restore_batch(prj_names.as_ref())
     }
     fn i_uninstall<T: AsRef<str>, Q: AsRef<[T]>>(
         &mut self,
         prj_names: Q,
-    ) -> Result<(), Self::Error> {
-        for prj in prj_names.as_ref() {
-            self.uninstall(prj)?;
-        }
-        Ok(())
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        self.uninstall_batch(prj_names.as_ref())
     }
 }
 
@@ -117,6 +114,80 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
         <Self as PMOperations>::Error::Interact(e)
     }
 
+    /// Reference access to the configuration that the manager has loaded
+    pub fn get_config(&self) -> &PMConfig {
+        &self.config
+    }
+
+    /// Send the output of the scripts to stderr instead of stdout, so that
+    /// stdout only has what the front end prints, like json
+    pub fn scripts_to_stderr(&mut self, to_stderr: bool) {
+        self.scripts_to_stderr = to_stderr;
+    }
+
+    /// Install a project like [`PMInteractive::i_install`], without asking
+    /// for the values that are set in `presets`. Its dependencies that aren't
+    /// installed are taken from `available` and installed first, see
+    /// [`PMProgrammatic::with_dependencies`]
+    pub fn i_install_with<T: AsRef<str>>(
+        &mut self,
+        url: T,
+        presets: I::Presets,
+        available: &[P],
+    ) -> Result<(), <Self as PMOperations>::Error> {
+        let _span = tracing::info_span!("install", url = url.as_ref()).entered();
+        let inter = I::new().map_err(Self::map_inter_error)?;
+        let prj_stub = inter
+            .url_to_download(url.as_ref(), &presets)
+            .map_err(Self::map_inter_error)?;
+        let (repo, git_dir) = self.download(&prj_stub)?;
+        // Nothing is kept of a download that can't be installed
+        let project =
+            match self.prepare_install(&inter, prj_stub, &repo, &git_dir, &presets, available) {
+                Ok(project) => project,
+                Err(e) => {
+                    std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+                    return Err(e);
+                }
+            };
+        self.get_mut_store()
+            .add(project.clone())
+            .map_err(Self::map_store_error)?;
+        self.mv(&project, &git_dir)?;
+        self.run_hook(&project, Hook::PreInstall)?;
+        self.build(&project)?;
+        self.run_hook(&project, Hook::PostInstall)?;
+        Ok(())
+    }
+
+    /// Turn a downloaded project into one that can be installed, checking its
+    /// requirements and installing its dependencies
+    fn prepare_install(
+        &mut self,
+        inter: &I,
+        prj_stub: P,
+        repo: &git2::Repository,
+        git_dir: &std::path::Path,
+        presets: &I::Presets,
+        available: &[P],
+    ) -> Result<P, <Self as PMOperations>::Error> {
+        let prj_stub = inter
+            .repo_to_checkout_branch(prj_stub, repo, presets)
+            .map_err(Self::map_inter_error)?;
+        self.switch_branch(&prj_stub, repo)?;
+        let project = inter
+            .create_project(&prj_stub, self.get_store(), git_dir, presets)
+            .map_err(Self::map_inter_error)?;
+        let dependencies = self.with_dependencies(vec![project.clone()], available)?;
+        self.check_requirements(&project)?;
+        for dep in dependencies {
+            if dep.get_name() != project.get_name() {
+                self.install(dep)?;
+            }
+        }
+        Ok(project)
+    }
+
     /// Uninstall a project, and then install it again
     /// Have you tried turning it off and on again?
     pub fn reinstall<T: AsRef<str>>(
@@ -126,54 +197,71 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
         let prj = self
             .get_store()
             .get_clone(prj_name.as_ref())
-            .ok_or(CommonPMErrors::NonExisting)?;
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
         self.uninstall(prj_name)?;
         self.install(prj)?;
         Ok(())
     }
-    /// Run the build script over an existing project.
+    /// The environment variables that the scripts of the projects are run with,
+    /// besides those of this process. `PREFIX` is the `prefix` of the
+    /// configuration, when it's set
+    pub fn script_env(&self) -> Vec<(&'static str, OsString)> {
+        let mut env = vec![];
+        if let Some(prefix) = &self.config.prefix {
+            env.push(("PREFIX", prefix.into()));
+        }
+        env
+    }
+    /// Run a command in the `src()` directory of a project, with the
+    /// environment of its scripts. Without a command, the shell of `$SHELL` is
+    /// opened. It shares the standard input and output of this process, and
+    /// returns the exit status of the command
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If the project has no sources
+    /// - Unable to start the command
+    pub fn exec<T: AsRef<str>>(
+        &self,
+        prj_name: T,
+        command: &[String],
+    ) -> Result<ExitStatus, <Self as PMOperations>::Error> {
+        let prj = self
+            .get_store()
+            .get_ref(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let src_dir = self.dirs.src().join(prj.get_dir());
+        if !src_dir.is_dir() {
+            Err(CommonPMErrors::MissingSources(prj.get_name().to_string()))?;
+        }
+        let exec = match command.split_first() {
+            Some((program, args)) => Exec::cmd(program).args(args),
+            None => Exec::cmd(std::env::var_os("SHELL").unwrap_or_else(|| "/bin/sh".into())),
+        };
+        tracing::debug!(?src_dir, ?command, "Running a command");
+        Ok(exec.cwd(src_dir).env_extend(&self.script_env()).join()?)
+    }
+    /// Run the build script over an existing project, and then over all the
+    /// projects that depend on it.
     pub fn rebuild<T: AsRef<str>>(&self, prj_name: T) -> Result<(), <Self as PMOperations>::Error> {
         let prj = self
             .get_store()
             .get_ref(prj_name.as_ref())
-            .ok_or(CommonPMErrors::NonExisting)?;
-        self.script_runner(prj.get_dir(), prj.get_install())?;
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        self.build(prj)?;
+        self.rebuild_dependents(prj_name)?;
         Ok(())
     }
     /// Clean all the files that might be left over from manually touching
-    /// config files or unsuccessful uninstallations
-    pub fn cleanup(&self) -> Result<(), <Self as PMOperations>::Error> {
-        let new_dir = self.get_dirs().git();
-        if new_dir.exists() {
-            std::fs::remove_dir_all(new_dir)?;
-        }
-        let src_dir = self.get_dirs().src();
-        if src_dir.exists() {
-            std::fs::read_dir(src_dir)?.try_for_each(|e| {
-                if let Ok(entry) = e {
-                    if self
-                        .get_store()
-                        .check_dir_free(entry.file_name().to_str().ok_or(CommonPMErrors::Os2Str)?)
-                    {
-                        std::fs::remove_dir_all(entry.path())?;
-                    }
-                }
-                Ok::<(), <Self as PMOperations>::Error>(())
-            })?;
-        }
-        let old_dir = self.get_dirs().old();
-        if old_dir.exists() {
-            std::fs::read_dir(&old_dir)?.try_for_each(|e| {
-                if let Ok(entry) = e {
-                    if !self
-                        .get_store()
-                        .check_dir_free(entry.file_name().to_str().ok_or(CommonPMErrors::Os2Str)?)
-                    {
-                        std::fs::remove_dir_all(entry.path())?;
-                    }
-                }
-                Ok::<(), <Self as PMOperations>::Error>(())
-            })?;
+    /// config files or unsuccessful uninstallations. These are the leftovers
+    /// and directories without projects that [`PMProgrammatic::diagnose`] finds
+    pub fn cleanup(&mut self) -> Result<(), <Self as PMOperations>::Error> {
+        for finding in self.diagnose()? {
+            if matches!(
+                finding,
+                Finding::LeftoverGit(_) | Finding::OrphanSources(_) | Finding::OrphanOld(_)
+            ) {
+                self.repair(&finding)?;
+            }
         }
         Ok(())
     }
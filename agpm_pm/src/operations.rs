@@ -1,9 +1,13 @@
+use crate::PMConfig;
 use crate::{Interactions, PMError, PrjManager};
-use amisgitpm::{Directories, PMOperations, ProjectIface, ProjectStore};
+use amisgitpm::{Directories, Hook, PMOperations, ProjectIface, ProjectStore};
 use fs_extra::dir::{self, CopyOptions};
+use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::path::Path;
-use subprocess::Exec;
+use std::time::{Duration, Instant};
+use subprocess::{Exec, Redirection};
+use tracing::{debug, warn};
 
 impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS>> PMOperations
     for PrjManager<P, D, PS, I>
@@ -15,11 +19,14 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
     type Error = PMError<D::Error, PS::Error, I::Error>;
     fn new() -> Result<Self, Self::Error> {
         let dirs = D::new().map_err(Self::Error::Dirs)?;
+        let config = PMConfig::load(&dirs.config())?;
         Ok(Self {
             dirs,
             store: PS::new().map_err(Self::Error::Store)?,
-            inter_data: PhantomData::default(),
-            p_data: PhantomData::default(),
+            config,
+            scripts_to_stderr: false,
+            inter_data: PhantomData,
+            p_data: PhantomData,
         })
     }
     fn map_store_error(err: <Self::Store as ProjectStore<P>>::Error) -> Self::Error {
@@ -37,6 +44,18 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
     fn get_dirs(&self) -> &Self::Dirs {
         &self.dirs
     }
+    fn get_global_hook(&self, hook: Hook) -> &[String] {
+        self.config.hooks.get(hook)
+    }
+    fn get_generations(&self) -> usize {
+        self.config.generations
+    }
+    fn get_fetch_jobs(&self) -> usize {
+        self.config.parallelism.fetch
+    }
+    fn get_build_jobs(&self) -> usize {
+        self.config.parallelism.build
+    }
     fn copy_directory<T: AsRef<Path>, Q: AsRef<Path>>(
         &self,
         from: T,
@@ -47,22 +66,76 @@ impl<P: ProjectIface, D: Directories, PS: ProjectStore<P>, I: Interactions<P, PS
             copy_inside: true,
             ..Default::default()
         };
-        dir::copy(from, to, &opts).map_err(|e|{ println!("{e}"); e})?;
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let start = Instant::now();
+        dir::copy(from, to, &opts).map_err(|e| {
+            warn!(?from, ?to, "Couldn't copy the directory: {e}");
+            Self::Error::FileExt(from.to_path_buf(), to.to_path_buf(), e)
+        })?;
+        debug!(?from, ?to, elapsed = ?start.elapsed(), "Copied");
         Ok(())
     }
     fn script_runner<T: AsRef<str>, Q: AsRef<[T]>>(
         &self,
+        prj_name: &str,
         dir: &str,
         script: Q,
     ) -> Result<(), Self::Error> {
         let src_dir = self.dirs.src().join(dir);
-        let script: Vec<&str> = script.as_ref().iter().map(|e| e.as_ref()).collect();
-        if !Exec::shell(script.join("&&"))
+        let script = script
+            .as_ref()
+            .iter()
+            .map(|e| e.as_ref())
+            .collect::<Vec<&str>>()
+            .join("&&");
+        debug!(dir, script, "Running a script");
+        let start = Instant::now();
+        let mut exec = Exec::shell(&script)
             .cwd(src_dir)
-            .join()?
-            .success()
-        {
-            Err(Self::Error::Exec)?;
+            .env_extend(&self.script_env());
+        // When several projects are built at once, the output of each script is
+        // shown in one piece after it ends, so that they don't mix
+        if self.config.parallelism.build > 1 {
+            exec = exec.stdout(Redirection::Pipe).stderr(Redirection::Merge);
+        } else if self.scripts_to_stderr {
+            exec = exec.stdout(Redirection::Merge);
+        }
+        let mut process = exec.popen()?;
+        let output = process.stdout.take().map(|mut out| {
+            std::thread::spawn(move || {
+                let mut output = vec![];
+                // Whatever could be read is shown
+                let _ = out.read_to_end(&mut output);
+                output
+            })
+        });
+        let status = match self.config.build_timeout {
+            Some(secs) => match process.wait_timeout(Duration::from_secs(secs))? {
+                Some(status) => status,
+                None => {
+                    warn!(dir, "Killed the script after {secs} seconds");
+                    process.kill()?;
+                    process.wait()?;
+                    // What it started may still hold the output, so it isn't
+                    // waited for
+                    Err(Self::Error::Timeout(prj_name.to_string(), secs))?
+                }
+            },
+            None => process.wait()?,
+        };
+        let output = output.map(|e| e.join().unwrap_or_default());
+        if let Some(output) = output.filter(|e| !e.is_empty()) {
+            let mut out: Box<dyn Write> = if self.scripts_to_stderr {
+                Box::new(std::io::stderr().lock())
+            } else {
+                Box::new(std::io::stdout().lock())
+            };
+            writeln!(out, "==> {dir}")?;
+            out.write_all(&output)?;
+        }
+        debug!(dir, ?status, elapsed = ?start.elapsed(), "Ran a script");
+        if !status.success() {
+            Err(Self::Error::Exec(prj_name.to_string(), script))?;
         }
         Ok(())
     }
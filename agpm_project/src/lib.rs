@@ -1,43 +1,35 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
-use amisgitpm::ProjectIface;
+pub use amisgitpm::UpdatePolicy;
+use amisgitpm::{Hook, ProjectIface};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
-/// What to do when updating a project
+/// Extra scripts to run at some points of the life of a project. The elements
+/// of each of them are joined with && before execution
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy, Default)]
-pub enum UpdatePolicy {
-    /// Update the project to the newest version every time
-    Always,
-    /// Ask whether to update or not
-    Ask,
-    /// Do not update the repo, **default** value
-    #[default]
-    Never,
-}
-
-impl std::fmt::Display for UpdatePolicy {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Always => {
-                write!(f, "Always try to update the project")
-            }
-            Self::Ask => {
-                write!(f, "Ask wether ot update or not")
-            }
-            Self::Never => {
-                write!(f, "Never try to update the project")
-            }
-        }
-    }
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    /// Run before building the project for the first time
+    pub pre_install: Vec<String>,
+    /// Run after building the project for the first time
+    pub post_install: Vec<String>,
+    /// Run before building a new version, for example to stop a running daemon
+    pub pre_update: Vec<String>,
+    /// Run after building a new version, for example to restart a daemon
+    pub post_update: Vec<String>,
+    /// Run before running the uninstall script
+    pub pre_uninstall: Vec<String>,
+    /// Run after the project is uninstalled
+    pub post_uninstall: Vec<String>,
 }
 
 /// `agpm`'s Project structure. It has one extra field. The `update_policy` stores
 /// information that determines behavior when interactively trying to update.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Project {
     /// The name of the projects
     pub name: String,
@@ -53,6 +45,18 @@ pub struct Project {
     pub install_script: Vec<String>,
     /// How to uninstall the project. The elements are joined with && before execution
     pub uninstall_script: Vec<String>,
+    /// The names of the projects that have to be installed before this one
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dependencies: Vec<String>,
+    /// The executables needed to build the project, like `cargo` or `meson>=0.60`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub requirements: Vec<String>,
+    /// Extra scripts to run at some points of the life of the project
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hooks: Hooks,
+    /// Free-form tags to select the project together with others
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Vec<String>,
 }
 
 impl ProjectIface for Project {
@@ -74,4 +78,33 @@ impl ProjectIface for Project {
     fn get_uninstall(&self) -> &[String] {
         &self.uninstall_script
     }
+    fn get_dependencies(&self) -> &[String] {
+        &self.dependencies
+    }
+    fn get_requirements(&self) -> &[String] {
+        &self.requirements
+    }
+    fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    fn get_update_policy(&self) -> UpdatePolicy {
+        self.update_policy
+    }
+    fn get_hook(&self, hook: Hook) -> &[String] {
+        match hook {
+            Hook::PreInstall => &self.hooks.pre_install,
+            Hook::PostInstall => &self.hooks.post_install,
+            Hook::PreUpdate => &self.hooks.pre_update,
+            Hook::PostUpdate => &self.hooks.post_update,
+            Hook::PreUninstall => &self.hooks.pre_uninstall,
+            Hook::PostUninstall => &self.hooks.post_uninstall,
+        }
+    }
+}
+
+// Every field added since the first schema has a default, so the records
+// written with older versions are read as they are
+#[cfg(feature = "store")]
+impl agpm_store::Versioned for Project {
+    const MIGRATIONS: &'static [agpm_store::Migration] = &[];
 }
@@ -0,0 +1,240 @@
+#[cfg(feature = "sqlite")]
+use crate::{HistoryEntry, SqliteStore};
+use crate::{ProjectStoreError, Store, TomlStore, Versioned};
+use agpm_config::Config;
+use amisgitpm::{Directories, ProjectIface, ProjectStore, Query};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The technologies that can be used to store the projects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A directory with a json file per project, the [`Store`]
+    #[default]
+    Json,
+    /// A single, hand editable, TOML file, the [`TomlStore`]
+    Toml,
+    /// A SQLite database that keeps the history of the projects, the `SqliteStore`
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+/// The configuration of the store. It is the `store` object of the configuration
+/// file, for example `{"store": {"backend": "toml"}}`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoreConfig {
+    /// Which technology to use
+    pub backend: Backend,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    store: StoreConfig,
+}
+
+impl StoreConfig {
+    /// Read the `store` configuration from a json configuration file, see
+    /// [`agpm_config::Config`]. If the file doesn't exist the default
+    /// configuration is used
+    /// # Errors
+    /// - Unable to read the file
+    /// - The file isn't a valid configuration
+    pub fn load<D>(path: &Path) -> Result<Self, ProjectStoreError<D>> {
+        Ok(Config::load(path)?.get::<ConfigFile>()?.store)
+    }
+}
+
+/// A [`ProjectStore`](amisgitpm::ProjectStore) that uses the backend chosen in
+/// the configuration file that is found at [`Directories::config`]
+pub enum AnyStore<D: Directories, T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned>
+{
+    /// Using the json tables backend
+    Json(Store<D, T>),
+    /// Using the TOML file backend
+    Toml(TomlStore<D, T>),
+    /// Using the SQLite backend
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore<D, T>),
+}
+
+impl<D: Directories, T> AnyStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    /// Open the store of a given backend, regardless of the configuration
+    /// # Errors
+    /// - Unable to open the store of that backend
+    pub fn open(backend: Backend) -> Result<Self, ProjectStoreError<D::Error>> {
+        Ok(match backend {
+            Backend::Json => Self::Json(Store::new()?),
+            Backend::Toml => Self::Toml(TomlStore::new()?),
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite => Self::Sqlite(SqliteStore::new()?),
+        })
+    }
+    /// Get which backend is being used
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::Json(_) => Backend::Json,
+            Self::Toml(_) => Backend::Toml,
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+    /// Get the history of a project, see [`SqliteStore::history`]
+    /// # Errors
+    /// - The backend doesn't keep a history -> `ProjectStoreError::NoHistory`
+    /// - Unable to read the history
+    #[cfg(feature = "sqlite")]
+    pub fn history(
+        &self,
+        prj_name: &str,
+    ) -> Result<Vec<HistoryEntry<T>>, ProjectStoreError<D::Error>> {
+        match self {
+            Self::Sqlite(store) => store.history(prj_name),
+            _ => Err(ProjectStoreError::NoHistory(self.backend())),
+        }
+    }
+    /// Get an entry of the history, see [`SqliteStore::history_entry`]
+    /// # Errors
+    /// - The backend doesn't keep a history -> `ProjectStoreError::NoHistory`
+    /// - There is no such entry, or it can't be read
+    #[cfg(feature = "sqlite")]
+    pub fn history_entry(&self, id: i64) -> Result<HistoryEntry<T>, ProjectStoreError<D::Error>> {
+        match self {
+            Self::Sqlite(store) => store.history_entry(id),
+            _ => Err(ProjectStoreError::NoHistory(self.backend())),
+        }
+    }
+}
+
+/// Copy all the projects of the `from` store into the `to` store, to change
+/// the backend that is used. Projects whose name or directory are already used
+/// in `to` are skipped. Returns the number of copied projects
+/// # Errors
+/// - Unable to add a project to `to`
+pub fn copy_store<T, F, S>(from: &F, to: &mut S) -> Result<usize, S::Error>
+where
+    T: ProjectIface,
+    F: ProjectStore<T>,
+    S: ProjectStore<T>,
+{
+    let mut copied = 0;
+    for prj in from.iter() {
+        if to.check_unique(prj.get_name(), prj.get_dir()) {
+            to.add(prj.clone())?;
+            copied += 1;
+        }
+    }
+    Ok(copied)
+}
+
+impl<D: Directories, T> ProjectStore<T> for AnyStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    type Error = ProjectStoreError<D::Error>;
+    fn new() -> Result<Self, Self::Error> {
+        let dirs = <D as Directories>::new().map_err(Self::Error::Dirs)?;
+        Self::open(StoreConfig::load(&dirs.config())?.backend)
+    }
+    fn add(&mut self, prj: T) -> Result<(), Self::Error> {
+        match self {
+            Self::Json(store) => store.add(prj),
+            Self::Toml(store) => store.add(prj),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.add(prj),
+        }
+    }
+    fn remove(&mut self, prj_name: &str) -> Result<(), Self::Error> {
+        match self {
+            Self::Json(store) => store.remove(prj_name),
+            Self::Toml(store) => store.remove(prj_name),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.remove(prj_name),
+        }
+    }
+    fn get_ref(&self, prj_name: &str) -> Option<&T> {
+        match self {
+            Self::Json(store) => store.get_ref(prj_name),
+            Self::Toml(store) => store.get_ref(prj_name),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.get_ref(prj_name),
+        }
+    }
+    fn get_clone(&self, prj_name: &str) -> Option<T> {
+        match self {
+            Self::Json(store) => store.get_clone(prj_name),
+            Self::Toml(store) => store.get_clone(prj_name),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.get_clone(prj_name),
+        }
+    }
+    fn check_dir_free(&self, dir: &str) -> bool {
+        match self {
+            Self::Json(store) => store.check_dir_free(dir),
+            Self::Toml(store) => store.check_dir_free(dir),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.check_dir_free(dir),
+        }
+    }
+    fn check_name_free(&self, prj_name: &str) -> bool {
+        match self {
+            Self::Json(store) => store.check_name_free(prj_name),
+            Self::Toml(store) => store.check_name_free(prj_name),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.check_name_free(prj_name),
+        }
+    }
+    fn check_unique(&self, prj_name: &str, dir: &str) -> bool {
+        match self {
+            Self::Json(store) => store.check_unique(prj_name, dir),
+            Self::Toml(store) => store.check_unique(prj_name, dir),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.check_unique(prj_name, dir),
+        }
+    }
+    fn query<'a>(&'a self, query: &Query<T>) -> Vec<&'a T> {
+        match self {
+            Self::Json(store) => store.query(query),
+            Self::Toml(store) => store.query(query),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.query(query),
+        }
+    }
+    fn find_by_dir<'a>(&'a self, dir: &str) -> Option<&'a T> {
+        match self {
+            Self::Json(store) => store.find_by_dir(dir),
+            Self::Toml(store) => store.find_by_dir(dir),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.find_by_dir(dir),
+        }
+    }
+    fn find_by_url<'a>(&'a self, url: &str) -> Vec<&'a T> {
+        match self {
+            Self::Json(store) => store.find_by_url(url),
+            Self::Toml(store) => store.find_by_url(url),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.find_by_url(url),
+        }
+    }
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
+        match self {
+            Self::Json(store) => store.iter(),
+            Self::Toml(store) => store.iter(),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.iter(),
+        }
+    }
+    fn is_empty(&self) -> bool {
+        match self {
+            Self::Json(store) => store.is_empty(),
+            Self::Toml(store) => store.is_empty(),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.is_empty(),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{BundleError, Versioned};
+use amisgitpm::ProjectIface;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+#[derive(Serialize, Deserialize)]
+struct Bundle<P> {
+    #[serde(default)]
+    schema_version: usize,
+    projects: Vec<P>,
+    /// The commit of each project, by name. Only lockfiles have them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    commits: BTreeMap<String, String>,
+}
+
+/// Write a set of projects to a portable json file, that can be read back with
+/// [`read_bundle`] in another machine or with a newer version of the project
+/// type
+/// # Errors
+/// - Unable to write to `writer`, or to write a project as json
+pub fn write_bundle<'a, T, I, W>(prjs: I, writer: W) -> Result<(), BundleError>
+where
+    T: Serialize + Versioned + 'a,
+    I: IntoIterator<Item = &'a T>,
+    W: Write,
+{
+    let bundle = Bundle {
+        schema_version: T::MIGRATIONS.len(),
+        projects: prjs.into_iter().collect(),
+        commits: BTreeMap::new(),
+    };
+    serde_json::to_writer_pretty(writer, &bundle)?;
+    Ok(())
+}
+
+/// Write a lockfile, a bundle in which every project is paired with the exact
+/// commit that has to be installed. It can be read with [`read_lockfile`], or
+/// with [`read_bundle`], ignoring the commits
+/// # Errors
+/// - Unable to write to `writer`, or to write a project as json
+pub fn write_lockfile<'a, T, I, W>(prjs: I, writer: W) -> Result<(), BundleError>
+where
+    T: ProjectIface + Serialize + Versioned + 'a,
+    I: IntoIterator<Item = (&'a T, String)>,
+    W: Write,
+{
+    let mut bundle = Bundle {
+        schema_version: T::MIGRATIONS.len(),
+        projects: vec![],
+        commits: BTreeMap::new(),
+    };
+    for (prj, commit) in prjs {
+        bundle.commits.insert(prj.get_name().to_string(), commit);
+        bundle.projects.push(prj);
+    }
+    serde_json::to_writer_pretty(writer, &bundle)?;
+    Ok(())
+}
+
+fn read<T, R>(reader: R) -> Result<(Vec<T>, BTreeMap<String, String>), BundleError>
+where
+    T: for<'d> Deserialize<'d> + Versioned,
+    R: Read,
+{
+    let bundle: Bundle<Value> = serde_json::from_reader(reader)?;
+    let current = T::MIGRATIONS.len();
+    if bundle.schema_version > current {
+        return Err(BundleError::NewerSchema(bundle.schema_version, current));
+    }
+    let prjs = bundle
+        .projects
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut record)| {
+            let name = record
+                .get("name")
+                .and_then(|e| e.as_str())
+                .map_or(format!("#{}", idx + 1), |e| e.to_string());
+            T::MIGRATIONS[bundle.schema_version..]
+                .iter()
+                .for_each(|migration| migration(&mut record));
+            T::deserialize(record).map_err(|e| BundleError::Record(name, e))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((prjs, bundle.commits))
+}
+
+/// Read the projects of a file written with [`write_bundle`], migrating them if
+/// they were written with an older version of the project type
+/// # Errors
+/// - Unable to read from `reader`, or it isn't a bundle
+/// - The bundle was written with a newer version -> `BundleError::NewerSchema`
+/// - A project can't be read -> `BundleError::Record`
+pub fn read_bundle<T, R>(reader: R) -> Result<Vec<T>, BundleError>
+where
+    T: for<'d> Deserialize<'d> + Versioned,
+    R: Read,
+{
+    Ok(read(reader)?.0)
+}
+
+/// Read the projects of a lockfile written with [`write_lockfile`], paired
+/// with their commits
+/// # Errors
+/// - The errors of [`read_bundle`]
+/// - If a project has no commit -> `BundleError::Unlocked`
+pub fn read_lockfile<T, R>(reader: R) -> Result<Vec<(T, String)>, BundleError>
+where
+    T: ProjectIface + for<'d> Deserialize<'d> + Versioned,
+    R: Read,
+{
+    let (prjs, mut commits) = read::<T, R>(reader)?;
+    prjs.into_iter()
+        .map(|prj| match commits.remove(prj.get_name()) {
+            Some(commit) => Ok((prj, commit)),
+            None => Err(BundleError::Unlocked(prj.get_name().to_string())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{prj, Prj};
+    use crate::Migration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn add_tags(record: &mut Value) {
+        record["tags"] = serde_json::json!(["migrated"]);
+    }
+
+    impl Versioned for Item {
+        const MIGRATIONS: &'static [Migration] = &[add_tags];
+    }
+
+    #[test]
+    fn locks_commits() {
+        let (a, b) = (prj("a"), prj("b"));
+        let mut out = vec![];
+        write_lockfile([(&a, "1234".to_string())], &mut out).unwrap();
+        let locked: Vec<(crate::test_utils::Prj, String)> = read_lockfile(out.as_slice()).unwrap();
+        assert_eq!(locked[0].1, "1234");
+        out.clear();
+        write_bundle([&b], &mut out).unwrap();
+        assert!(matches!(
+            read_lockfile::<Prj, _>(out.as_slice()),
+            Err(BundleError::Unlocked(name)) if name == "b"
+        ));
+    }
+
+    #[test]
+    fn round_trips_and_migrates() {
+        let items = vec![Item {
+            name: "a".into(),
+            tags: vec![],
+        }];
+        let mut out = vec![];
+        write_bundle(&items, &mut out).unwrap();
+        assert_eq!(read_bundle::<Item, _>(out.as_slice()).unwrap(), items);
+        let old = r#"{"projects": [{"name": "b"}]}"#;
+        let migrated: Vec<Item> = read_bundle(old.as_bytes()).unwrap();
+        assert_eq!(migrated[0].tags, vec!["migrated"]);
+        let newer = r#"{"schema_version": 2, "projects": []}"#;
+        assert!(matches!(
+            read_bundle::<Item, _>(newer.as_bytes()),
+            Err(BundleError::NewerSchema(2, 1))
+        ));
+    }
+}
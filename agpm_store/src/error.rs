@@ -1,5 +1,8 @@
+use agpm_config::ConfigError;
+use amisgitpm::Hint;
 use json_tables::{TableBuilderError, TableError};
 use thiserror::Error;
+use toml_edit::TomlError;
 
 /// An error for the [`ProjectStore`](amisgitpm::ProjectStore) struct
 #[non_exhaustive]
@@ -14,4 +17,87 @@ pub enum ProjectStoreError<D> {
     /// An error while creating directories
     #[error(transparent)]
     Dirs(D),
+    /// An error while reading, migrating or backing up the records
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// A record of the store can't be read. Holds the name of the record
+    #[error("The record {0} of the store can't be read: {1}")]
+    Record(String, serde_json::Error),
+    /// The store was written by a newer version. Holds the version of the store
+    /// and the newest supported version
+    #[error("The store has schema version {0}, but only up to {1} is supported")]
+    NewerSchema(usize, usize),
+    /// The file that holds the version of the store is corrupted
+    #[error("The store has a bad schema version: {0:?}")]
+    BadVersion(String),
+    /// The TOML file of the store isn't valid TOML
+    #[error(transparent)]
+    Toml(#[from] TomlError),
+    /// The TOML file of the store doesn't have the expected structure
+    #[error(transparent)]
+    TomlDe(#[from] toml_edit::de::Error),
+    /// A project can't be written as TOML
+    #[error(transparent)]
+    TomlSer(#[from] toml_edit::ser::Error),
+    /// The projects in the TOML file are not a list of `[[project]]` tables
+    #[error("The projects must be written as [[project]] tables")]
+    BadManifest,
+    /// Two projects use the same name or directory. Holds the repeated value
+    #[error("More than one project uses the name or directory {0:?}")]
+    Duplicate(String),
+    /// Attempting to remove a project that is not in the store
+    #[error("There is no project named {0:?} in the store")]
+    NonExisting(String),
+    /// The configuration file can't be read
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// An error with the SQLite database
+    #[cfg(feature = "sqlite")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    /// The backend in use doesn't keep the history of the projects
+    #[error("The {0:?} store backend doesn't keep a history of the projects")]
+    NoHistory(crate::Backend),
+}
+
+/// An error while writing or reading a bundle of projects
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum BundleError {
+    /// The bundle isn't valid json, or it can't be read or written
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// A project of the bundle can't be read. Holds the name of the project
+    #[error("The project {0} of the bundle can't be read: {1}")]
+    Record(String, serde_json::Error),
+    /// The bundle was written by a newer version. Holds the version of the
+    /// bundle and the newest supported version
+    #[error("The bundle has schema version {0}, but only up to {1} is supported")]
+    NewerSchema(usize, usize),
+    /// A project of a lockfile has no commit. Holds the name of the project
+    #[error("The project {0} isn't locked to any commit")]
+    Unlocked(String),
+}
+
+impl<D> Hint for ProjectStoreError<D> {
+    fn hint(&self) -> Option<String> {
+        Some(match self {
+            Self::Dirs(_) => {
+                "Check that `$HOME` is set, that the `dirs` of the configuration file are right, \
+                 and that the directories of agpm can be created"
+                    .into()
+            }
+            Self::NewerSchema(..) => "Update agpm to read the store".into(),
+            Self::NonExisting(prj) => {
+                format!("Check the name {prj:?} against the installed projects")
+            }
+            Self::Config(_) => {
+                "Fix the configuration file, or remove it to use the defaults".into()
+            }
+            Self::NoHistory(_) => "Set the store backend to sqlite in the configuration file \
+                                   to keep the history of the projects"
+                .into(),
+            _ => "Find and fix the problems of the store with `agpm doctor --fix`".into(),
+        })
+    }
 }
@@ -2,44 +2,84 @@
 #![doc = include_str!("../README.md")]
 
 use amisgitpm::{Directories, ProjectIface, ProjectStore};
-use json_tables::{Deserialize, Serialize, Table, TableError};
+use json_tables::{Deserialize, Serialize, Table};
 use std::marker::PhantomData;
+use std::time::Instant;
+use tracing::debug;
 
 mod error;
-pub use error::ProjectStoreError;
+pub use error::{BundleError, ProjectStoreError};
+mod schema;
+pub use schema::{Migration, UnreadableRecord, Versioned};
+mod bundle;
+pub use bundle::{read_bundle, read_lockfile, write_bundle, write_lockfile};
+mod memory_store;
+pub use memory_store::MemoryStore;
+mod toml_store;
+pub use toml_store::TomlStore;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
+#[cfg(feature = "sqlite")]
+pub use sqlite_store::{Action, HistoryEntry, SqliteStore};
+mod backend;
+#[cfg(test)]
+mod test_utils;
+pub use backend::{copy_store, AnyStore, Backend, StoreConfig};
 
 /// A struct that implements the [`ProjectStore`](amisgitpm::ProjectStore)
 /// trait using a [`json_tables::Table`]
-pub struct Store<D: Directories, T: ProjectIface + Serialize + for<'d> Deserialize<'d>> {
+///
+/// The table directory holds the version of the schema of its records. When the
+/// store is opened, outdated records are migrated with the [`Versioned`]
+/// migrations of `T`, after making a backup of the table.
+pub struct Store<D: Directories, T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned>
+{
     table: Table<T>,
     dirs: PhantomData<D>,
 }
 
+impl<D: Directories, T> Store<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    /// Find the records that keep the store from being opened, without
+    /// opening it. Set them aside with [`UnreadableRecord::set_aside`]
+    /// # Errors
+    /// - The store was written with a newer schema -> `ProjectStoreError::NewerSchema`
+    /// - The version file is corrupted -> `ProjectStoreError::BadVersion`
+    /// - Unable to read the records
+    pub fn unreadable_records() -> Result<Vec<UnreadableRecord>, ProjectStoreError<D::Error>> {
+        let db = <D as Directories>::new()
+            .map_err(ProjectStoreError::Dirs)?
+            .projects_db();
+        if !db.exists() {
+            return Ok(vec![]);
+        }
+        schema::unreadable::<T, D::Error>(&db)
+    }
+}
+
 impl<D: Directories, T> ProjectStore<T> for Store<D, T>
 where
-    T: ProjectIface + Serialize + for<'d> Deserialize<'d>,
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
 {
     type Error = ProjectStoreError<D::Error>;
     fn new() -> Result<Self, Self::Error> {
         let dirs = <D as Directories>::new().map_err(Self::Error::Dirs)?;
-        match Table::builder(dirs.projects_db()).load() {
-            Ok(table) => Ok(Store {
+        let db = dirs.projects_db();
+        if !db.exists() {
+            let table = Table::builder(&db).set_auto_write().build()?;
+            schema::write_version::<T, D::Error>(&db)?;
+            return Ok(Store {
                 table,
-                dirs: PhantomData::default(),
-            }),
-            Err(e) => match e {
-                TableError::FileOpError(io_err) => match io_err.kind() {
-                    std::io::ErrorKind::NotFound => Ok(Store {
-                        table: Table::builder(dirs.projects_db())
-                            .set_auto_write()
-                            .build()?,
-                        dirs: PhantomData::default(),
-                    }),
-                    _ => Err(TableError::FileOpError(io_err))?,
-                },
-                _ => Err(e)?,
-            },
+                dirs: PhantomData,
+            });
         }
+        schema::prepare::<T, D::Error>(&db)?;
+        Ok(Store {
+            table: Table::builder(&db).load()?,
+            dirs: PhantomData,
+        })
     }
     fn check_name_free(&self, prj_name: &str) -> bool {
         !self
@@ -67,14 +107,18 @@ where
     }
     fn add(&mut self, prj: T) -> Result<(), Self::Error> {
         let name = prj.get_name().to_string();
+        let start = Instant::now();
         self.table.push(&name, prj)?;
+        debug!(project = name, elapsed = ?start.elapsed(), "Added to the json store");
         Ok(())
     }
     fn remove(&mut self, prj_name: &str) -> Result<(), Self::Error> {
+        let start = Instant::now();
         self.table.pop(prj_name)?;
+        debug!(project = prj_name, elapsed = ?start.elapsed(), "Removed from the json store");
         Ok(())
     }
-    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &T> + 'a> {
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
         Box::new(self.table.get_table_content().map(|e| &e.info))
     }
     fn is_empty(&self) -> bool {
@@ -0,0 +1,103 @@
+use crate::ProjectStoreError;
+use amisgitpm::{ProjectIface, ProjectStore};
+use std::convert::Infallible;
+
+/// A struct that implements the [`ProjectStore`](amisgitpm::ProjectStore)
+/// trait keeping the projects in memory only. They are lost when it's dropped
+///
+/// It needs no directories, so it's useful for tests and for programs that
+/// embed a project manager, together with `agpm_dirs::TempDirs`.
+pub struct MemoryStore<T: ProjectIface> {
+    projects: Vec<T>,
+}
+
+impl<T: ProjectIface> From<Vec<T>> for MemoryStore<T> {
+    /// A store that starts with some projects. Repeated names or directories
+    /// aren't checked
+    fn from(projects: Vec<T>) -> Self {
+        Self { projects }
+    }
+}
+
+impl<T: ProjectIface> ProjectStore<T> for MemoryStore<T> {
+    type Error = ProjectStoreError<Infallible>;
+    fn new() -> Result<Self, Self::Error> {
+        Ok(Self { projects: vec![] })
+    }
+    fn check_name_free(&self, prj_name: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_name() == prj_name)
+    }
+    fn check_dir_free(&self, dir: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_dir() == dir)
+    }
+    fn get_ref(&self, prj_name: &str) -> Option<&T> {
+        self.projects.iter().find(|e| e.get_name() == prj_name)
+    }
+    fn get_clone(&self, prj_name: &str) -> Option<T> {
+        self.get_ref(prj_name).cloned()
+    }
+    fn add(&mut self, prj: T) -> Result<(), Self::Error> {
+        if !self.check_name_free(prj.get_name()) {
+            return Err(ProjectStoreError::Duplicate(prj.get_name().to_string()));
+        }
+        self.projects.push(prj);
+        Ok(())
+    }
+    fn remove(&mut self, prj_name: &str) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(prj_name.to_string()))?;
+        self.projects.remove(idx);
+        Ok(())
+    }
+    fn edit(&mut self, old_prj_name: &str, new_prj: T) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == old_prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(old_prj_name.to_string()))?;
+        if new_prj.get_name() != old_prj_name && !self.check_name_free(new_prj.get_name()) {
+            return Err(ProjectStoreError::Duplicate(new_prj.get_name().to_string()));
+        }
+        self.projects[idx] = new_prj;
+        Ok(())
+    }
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
+        Box::new(self.projects.iter())
+    }
+    fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{prj, Prj};
+
+    #[test]
+    fn keeps_projects() {
+        let mut store = MemoryStore::<Prj>::new().unwrap();
+        store.add(prj("a")).unwrap();
+        store.add(prj("b")).unwrap();
+        assert!(matches!(
+            store.add(prj("a")),
+            Err(ProjectStoreError::Duplicate(_))
+        ));
+        assert!(matches!(
+            store.edit("a", prj("b")),
+            Err(ProjectStoreError::Duplicate(_))
+        ));
+        store.edit("a", prj("c")).unwrap();
+        let names: Vec<&str> = store.iter().map(|e| e.get_name()).collect();
+        assert_eq!(names, vec!["c", "b"]);
+        store.remove("c").unwrap();
+        assert!(matches!(
+            store.remove("c"),
+            Err(ProjectStoreError::NonExisting(_))
+        ));
+        assert!(!store.is_empty());
+    }
+}
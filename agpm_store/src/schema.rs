@@ -0,0 +1,264 @@
+use crate::ProjectStoreError;
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the file, inside the table directory, that holds the version of
+/// the records schema. It has no `.json` extension so that the table ignores it.
+const VERSION_FILE: &str = "schema_version";
+
+/// A function that takes a record from one version of the schema to the next one
+pub type Migration = fn(&mut Value);
+
+/// A trait for the items of the store, so that records written with older
+/// versions of the item can still be read
+pub trait Versioned {
+    /// The migrations, in order. The migration at index `i` takes a record from
+    /// version `i` to version `i + 1`, so the current version of the schema is
+    /// the number of migrations. Stores without a version are at version 0
+    const MIGRATIONS: &'static [Migration];
+}
+
+/// Read the schema version of a table directory
+fn read_version<D>(db: &Path) -> Result<usize, ProjectStoreError<D>> {
+    match fs::read_to_string(db.join(VERSION_FILE)) {
+        Ok(version) => version
+            .trim()
+            .parse()
+            .map_err(|_| ProjectStoreError::BadVersion(version.trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write the current schema version to a table directory
+pub(crate) fn write_version<T: Versioned, D>(db: &Path) -> Result<(), ProjectStoreError<D>> {
+    fs::write(db.join(VERSION_FILE), T::MIGRATIONS.len().to_string())?;
+    Ok(())
+}
+
+/// Get all the records of a table directory, with their names
+fn records(db: &Path) -> Result<Vec<(String, PathBuf)>, std::io::Error> {
+    let mut records = vec![];
+    for entry in fs::read_dir(db)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension() == Some(OsStr::new("json")) {
+            if let Some(name) = path.file_stem().and_then(|e| e.to_str()) {
+                records.push((name.to_string(), path.clone()));
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// A record of a table directory that can't be read, found with
+/// [`Store::unreadable_records`](crate::Store::unreadable_records)
+#[derive(Debug)]
+pub struct UnreadableRecord {
+    /// The name of the record, which is the name of its project
+    pub name: String,
+    /// The file of the record
+    pub path: PathBuf,
+    /// Why it can't be read
+    pub error: serde_json::Error,
+}
+
+impl std::fmt::Display for UnreadableRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The record {} of the store can't be read: {}",
+            self.name, self.error
+        )
+    }
+}
+
+impl UnreadableRecord {
+    /// Move the record out of the table, into `{db}.unreadable`, so that the
+    /// store can be opened and the record can still be fixed by hand. Returns
+    /// where the record was moved
+    /// # Errors
+    /// - Unable to create the directory or to move the record
+    pub fn set_aside(&self) -> std::io::Result<PathBuf> {
+        let mut aside = self
+            .path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .as_os_str()
+            .to_owned();
+        aside.push(".unreadable");
+        let aside = PathBuf::from(aside);
+        fs::create_dir_all(&aside)?;
+        let to = aside.join(self.path.file_name().unwrap_or_default());
+        fs::rename(&self.path, &to)?;
+        Ok(to)
+    }
+}
+
+/// Read a record, taking it from the `version` of the schema to the current one
+fn read_record<T: Versioned>(
+    path: &Path,
+    version: usize,
+) -> std::io::Result<Result<Value, serde_json::Error>> {
+    let mut record: Value = match serde_json::from_str(&fs::read_to_string(path)?) {
+        Ok(record) => record,
+        Err(e) => return Ok(Err(e)),
+    };
+    T::MIGRATIONS[version..]
+        .iter()
+        .for_each(|migration| migration(&mut record));
+    Ok(Ok(record))
+}
+
+/// Get the records of a table directory that can't be read as a `T`, without
+/// changing them
+/// # Errors
+/// - The table was written with a newer schema -> `ProjectStoreError::NewerSchema`
+/// - The version file is corrupted -> `ProjectStoreError::BadVersion`
+/// - Unable to read the files
+pub(crate) fn unreadable<T, D>(db: &Path) -> Result<Vec<UnreadableRecord>, ProjectStoreError<D>>
+where
+    T: Versioned + for<'d> serde::Deserialize<'d>,
+{
+    let version = read_version(db)?;
+    let current = T::MIGRATIONS.len();
+    if version > current {
+        return Err(ProjectStoreError::NewerSchema(version, current));
+    }
+    let mut unreadable = vec![];
+    for (name, path) in records(db)? {
+        let read =
+            read_record::<T>(&path, version)?.and_then(|record| T::deserialize(record).map(|_| ()));
+        if let Err(error) = read {
+            unreadable.push(UnreadableRecord { name, path, error });
+        }
+    }
+    Ok(unreadable)
+}
+
+/// Copy the records of a table directory into `{db}.backup-v{version}`
+fn backup<D>(db: &Path, version: usize) -> Result<PathBuf, ProjectStoreError<D>> {
+    let mut backup = db.as_os_str().to_owned();
+    backup.push(format!(".backup-v{version}"));
+    let backup = PathBuf::from(backup);
+    if backup.exists() {
+        fs::remove_dir_all(&backup)?;
+    }
+    fs::create_dir_all(&backup)?;
+    for entry in fs::read_dir(db)? {
+        let path = entry?.path();
+        if let (true, Some(name)) = (path.is_file(), path.file_name()) {
+            fs::copy(&path, backup.join(name))?;
+        }
+    }
+    Ok(backup)
+}
+
+/// Take an existing table directory to the current schema version, and check
+/// that every record in it can be read as a `T`.
+///
+/// If the table is outdated, a backup is made before migrating every record
+/// # Errors
+/// - The table was written with a newer schema -> `ProjectStoreError::NewerSchema`
+/// - The version file is corrupted -> `ProjectStoreError::BadVersion`
+/// - A record can't be read or migrated -> `ProjectStoreError::Record`
+/// - Unable to read, write or backup the files
+pub(crate) fn prepare<T, D>(db: &Path) -> Result<(), ProjectStoreError<D>>
+where
+    T: Versioned + for<'d> serde::Deserialize<'d>,
+{
+    let version = read_version(db)?;
+    let current = T::MIGRATIONS.len();
+    if version > current {
+        return Err(ProjectStoreError::NewerSchema(version, current));
+    }
+    if version < current {
+        backup(db, version)?;
+    }
+    for (name, path) in records(db)? {
+        let record_err = |source| ProjectStoreError::Record(name.clone(), source);
+        let record = read_record::<T>(&path, version)?.map_err(record_err)?;
+        if version < current {
+            fs::write(
+                &path,
+                serde_json::to_string_pretty(&record).map_err(record_err)?,
+            )?;
+        }
+        T::deserialize(record).map_err(record_err)?;
+    }
+    if version < current {
+        write_version::<T, D>(db)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Item {
+        #[allow(dead_code)]
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn add_tags(record: &mut Value) {
+        record["tags"] = serde_json::json!(["migrated"]);
+    }
+
+    impl Versioned for Item {
+        const MIGRATIONS: &'static [Migration] = &[add_tags];
+    }
+
+    fn table(name: &str) -> PathBuf {
+        let db = std::env::temp_dir().join(format!("agpm_store_{name}_{}", std::process::id()));
+        if db.exists() {
+            fs::remove_dir_all(&db).unwrap();
+        }
+        fs::create_dir_all(&db).unwrap();
+        db
+    }
+
+    #[test]
+    fn migrates_and_backs_up() {
+        let db = table("migrates");
+        fs::write(db.join("a.json"), r#"{"name": "a"}"#).unwrap();
+        prepare::<Item, ()>(&db).unwrap();
+        let item: Item =
+            serde_json::from_str(&fs::read_to_string(db.join("a.json")).unwrap()).unwrap();
+        assert_eq!(item.tags, vec!["migrated"]);
+        assert_eq!(read_version::<()>(&db).unwrap(), 1);
+        let backup = PathBuf::from(format!("{}.backup-v0", db.display()));
+        assert!(backup.join("a.json").exists());
+        fs::remove_dir_all(db).unwrap();
+        fs::remove_dir_all(backup).unwrap();
+    }
+
+    #[test]
+    fn reports_bad_records() {
+        let db = table("reports");
+        write_version::<Item, ()>(&db).unwrap();
+        fs::write(db.join("good.json"), r#"{"name": "good", "tags": []}"#).unwrap();
+        fs::write(db.join("bad.json"), r#"{"name": "bad"}"#).unwrap();
+        match prepare::<Item, ()>(&db) {
+            Err(ProjectStoreError::Record(name, _)) => assert_eq!(name, "bad"),
+            _ => panic!("The bad record went unreported"),
+        }
+        let found = unreadable::<Item, ()>(&db).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "bad");
+        let aside = found[0].set_aside().unwrap();
+        assert!(aside.exists());
+        prepare::<Item, ()>(&db).unwrap();
+        fs::remove_dir_all(aside.parent().unwrap()).unwrap();
+        fs::write(db.join("schema_version"), "2").unwrap();
+        assert!(matches!(
+            prepare::<Item, ()>(&db),
+            Err(ProjectStoreError::NewerSchema(2, 1))
+        ));
+        fs::remove_dir_all(db).unwrap();
+    }
+}
@@ -0,0 +1,397 @@
+use crate::{ProjectStoreError, Versioned};
+use amisgitpm::{Directories, ProjectIface, ProjectStore};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// Every entry of the history of a project has the `id` of its first entry as
+/// `project`, which doesn't change when the project is renamed
+const CREATE: &str = "
+CREATE TABLE IF NOT EXISTS projects (
+    name TEXT PRIMARY KEY,
+    dir  TEXT NOT NULL UNIQUE,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS history (
+    id      INTEGER PRIMARY KEY AUTOINCREMENT,
+    name    TEXT NOT NULL,
+    action  TEXT NOT NULL,
+    time    INTEGER NOT NULL,
+    data    TEXT NOT NULL,
+    project INTEGER
+);
+CREATE INDEX IF NOT EXISTS history_name ON history (name);
+CREATE INDEX IF NOT EXISTS history_project ON history (project);
+";
+
+/// The change that a [`HistoryEntry`] records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// The project was added to the store
+    Add,
+    /// The project was edited. The entry holds the new configuration
+    Edit,
+    /// The project was removed from the store. The entry holds its last
+    /// configuration
+    Remove,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Edit => "edit",
+            Self::Remove => "remove",
+        }
+    }
+    fn parse(action: &str) -> Self {
+        match action {
+            "add" => Self::Add,
+            "remove" => Self::Remove,
+            _ => Self::Edit,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A configuration that a project had at some point
+#[derive(Debug, Clone)]
+pub struct HistoryEntry<T> {
+    /// The identifier of the entry, increasing with every change of the store
+    pub id: i64,
+    /// When the change happened, in seconds since the unix epoch
+    pub time: u64,
+    /// What happened to the project
+    pub action: Action,
+    /// The configuration of the project after the change, or before it, if it
+    /// was removed
+    pub project: T,
+}
+
+/// A struct that implements the [`ProjectStore`](amisgitpm::ProjectStore)
+/// trait using a SQLite database.
+///
+/// The database is the `{projects_db}.sqlite` file. Every change to the store
+/// is also written to an append-only history, that can be read with
+/// [`SqliteStore::history`]. The schema version of the records is kept in the
+/// `user_version` of the database, and outdated databases are migrated with the
+/// [`Versioned`] migrations of `T`, including their history, after copying them
+/// to `{projects_db}.sqlite.backup-v{version}`.
+pub struct SqliteStore<
+    D: Directories,
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+> {
+    // Behind a mutex so that the store can be shared by the threads of a
+    // project manager
+    conn: Mutex<Connection>,
+    projects: Vec<T>,
+    dirs: PhantomData<D>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |e| e.as_secs())
+}
+
+impl<D: Directories, T> SqliteStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    /// Get the path to the database, given the directories
+    pub fn path(dirs: &D) -> PathBuf {
+        dirs.projects_db().with_extension("sqlite")
+    }
+    fn open(path: &Path) -> Result<Self, ProjectStoreError<D::Error>> {
+        let existed = path.exists();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch(CREATE)?;
+        let current = T::MIGRATIONS.len();
+        let version: usize = if existed {
+            conn.query_row("PRAGMA user_version", [], |row| row.get(0))?
+        } else {
+            conn.pragma_update(None, "user_version", current)?;
+            current
+        };
+        if version > current {
+            return Err(ProjectStoreError::NewerSchema(version, current));
+        }
+        if version < current {
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(format!(".backup-v{version}"));
+            std::fs::copy(path, backup)?;
+            Self::migrate(&mut conn, version)?;
+        }
+        let projects = {
+            let mut stmt = conn.prepare("SELECT name, data FROM projects ORDER BY rowid")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            let mut projects = vec![];
+            for row in rows {
+                let (name, data): (String, String) = row?;
+                projects.push(
+                    serde_json::from_str(&data).map_err(|e| ProjectStoreError::Record(name, e))?,
+                );
+            }
+            projects
+        };
+        Ok(Self {
+            conn: Mutex::new(conn),
+            projects,
+            dirs: PhantomData,
+        })
+    }
+    /// The connection, for the methods that don't change the store
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// The connection, for the methods that change the store
+    fn conn_mut(&mut self) -> &mut Connection {
+        self.conn.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+    fn migrate(conn: &mut Connection, version: usize) -> Result<(), ProjectStoreError<D::Error>> {
+        let tx = conn.transaction()?;
+        for table in ["projects", "history"] {
+            let rows: Vec<(i64, String, String)> = {
+                let mut stmt = tx.prepare(&format!("SELECT rowid, name, data FROM {table}"))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+                rows.collect::<Result<_, _>>()?
+            };
+            for (rowid, name, data) in rows {
+                let record_err = |source| ProjectStoreError::Record(name.clone(), source);
+                let mut record: Value = serde_json::from_str(&data).map_err(record_err)?;
+                T::MIGRATIONS[version..]
+                    .iter()
+                    .for_each(|migration| migration(&mut record));
+                let data = serde_json::to_string(&record).map_err(record_err)?;
+                tx.execute(
+                    &format!("UPDATE {table} SET data = ?1 WHERE rowid = ?2"),
+                    params![data, rowid],
+                )?;
+            }
+        }
+        tx.pragma_update(None, "user_version", T::MIGRATIONS.len())?;
+        tx.commit()?;
+        Ok(())
+    }
+    /// Record a change of the project that is named `name`, and was named
+    /// `old_name` before it, in the history of that project
+    fn log(
+        tx: &rusqlite::Transaction,
+        action: Action,
+        old_name: &str,
+        name: &str,
+        data: &str,
+    ) -> Result<(), rusqlite::Error> {
+        let (project, last): (Option<i64>, Option<String>) = tx
+            .query_row(
+                "SELECT project, action FROM history WHERE name = ?1 ORDER BY id DESC LIMIT 1",
+                [old_name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .unwrap_or_default();
+        // An added project continues the history of a removed one with its
+        // name, but not that of a project that was renamed
+        let project = match (action, last.map(|e| Action::parse(&e))) {
+            (Action::Add, Some(Action::Remove)) | (Action::Edit | Action::Remove, _) => project,
+            (Action::Add, _) => None,
+        };
+        tx.execute(
+            "INSERT INTO history (name, action, time, data, project) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name, action.as_str(), now(), data, project],
+        )?;
+        if project.is_none() {
+            tx.execute(
+                "UPDATE history SET project = id WHERE id = ?1",
+                [tx.last_insert_rowid()],
+            )?;
+        }
+        Ok(())
+    }
+    fn to_data(prj: &T) -> Result<String, ProjectStoreError<D::Error>> {
+        serde_json::to_string(prj)
+            .map_err(|e| ProjectStoreError::Record(prj.get_name().to_string(), e))
+    }
+    /// Get all the configurations that a project with name `prj_name` has had,
+    /// from the oldest to the newest, including those from before it was
+    /// renamed
+    /// # Errors
+    /// - Unable to read the database
+    /// - A configuration can't be read -> `ProjectStoreError::Record`
+    pub fn history(
+        &self,
+        prj_name: &str,
+    ) -> Result<Vec<HistoryEntry<T>>, ProjectStoreError<D::Error>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, action, time, data FROM history WHERE project = (
+                SELECT project FROM history WHERE name = ?1 ORDER BY id DESC LIMIT 1
+            ) ORDER BY id",
+        )?;
+        let rows = stmt.query_map([prj_name], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        let mut entries = vec![];
+        for row in rows {
+            let (id, action, time, data): (i64, String, u64, String) = row?;
+            entries.push(HistoryEntry {
+                id,
+                time,
+                action: Action::parse(&action),
+                project: serde_json::from_str(&data)
+                    .map_err(|e| ProjectStoreError::Record(prj_name.to_string(), e))?,
+            });
+        }
+        Ok(entries)
+    }
+    /// Get the configuration recorded in the history entry with identifier `id`
+    /// # Errors
+    /// - There is no such entry -> `ProjectStoreError::NonExisting`
+    /// - Unable to read the database or the configuration
+    pub fn history_entry(&self, id: i64) -> Result<HistoryEntry<T>, ProjectStoreError<D::Error>> {
+        let (name, action, time, data): (String, String, u64, String) = self
+            .conn()
+            .query_row(
+                "SELECT name, action, time, data FROM history WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?
+            .ok_or_else(|| ProjectStoreError::NonExisting(format!("#{id}")))?;
+        Ok(HistoryEntry {
+            id,
+            time,
+            action: Action::parse(&action),
+            project: serde_json::from_str(&data).map_err(|e| ProjectStoreError::Record(name, e))?,
+        })
+    }
+}
+
+impl<D: Directories, T> ProjectStore<T> for SqliteStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    type Error = ProjectStoreError<D::Error>;
+    fn new() -> Result<Self, Self::Error> {
+        let dirs = <D as Directories>::new().map_err(Self::Error::Dirs)?;
+        Self::open(&Self::path(&dirs))
+    }
+    fn add(&mut self, prj: T) -> Result<(), Self::Error> {
+        let data = Self::to_data(&prj)?;
+        let start = Instant::now();
+        let tx = self.conn_mut().transaction()?;
+        tx.execute(
+            "INSERT INTO projects (name, dir, data) VALUES (?1, ?2, ?3)",
+            params![prj.get_name(), prj.get_dir(), data],
+        )?;
+        Self::log(&tx, Action::Add, prj.get_name(), prj.get_name(), &data)?;
+        tx.commit()?;
+        debug!(project = prj.get_name(), elapsed = ?start.elapsed(), "Added to the sqlite store");
+        self.projects.push(prj);
+        Ok(())
+    }
+    fn remove(&mut self, prj_name: &str) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(prj_name.to_string()))?;
+        let data = Self::to_data(&self.projects[idx])?;
+        let start = Instant::now();
+        let tx = self.conn_mut().transaction()?;
+        tx.execute("DELETE FROM projects WHERE name = ?1", [prj_name])?;
+        Self::log(&tx, Action::Remove, prj_name, prj_name, &data)?;
+        tx.commit()?;
+        debug!(project = prj_name, elapsed = ?start.elapsed(), "Removed from the sqlite store");
+        self.projects.remove(idx);
+        Ok(())
+    }
+    fn edit(&mut self, old_prj_name: &str, new_prj: T) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == old_prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(old_prj_name.to_string()))?;
+        let data = Self::to_data(&new_prj)?;
+        let start = Instant::now();
+        let tx = self.conn_mut().transaction()?;
+        tx.execute(
+            "UPDATE projects SET name = ?1, dir = ?2, data = ?3 WHERE name = ?4",
+            params![new_prj.get_name(), new_prj.get_dir(), data, old_prj_name],
+        )?;
+        Self::log(&tx, Action::Edit, old_prj_name, new_prj.get_name(), &data)?;
+        tx.commit()?;
+        debug!(project = new_prj.get_name(), elapsed = ?start.elapsed(), "Edited in the sqlite store");
+        self.projects[idx] = new_prj;
+        Ok(())
+    }
+    fn get_ref(&self, prj_name: &str) -> Option<&T> {
+        self.projects.iter().find(|e| e.get_name() == prj_name)
+    }
+    fn get_clone(&self, prj_name: &str) -> Option<T> {
+        self.get_ref(prj_name).cloned()
+    }
+    fn check_dir_free(&self, dir: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_dir() == dir)
+    }
+    fn check_name_free(&self, prj_name: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_name() == prj_name)
+    }
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
+        Box::new(self.projects.iter())
+    }
+    fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{prj, Prj};
+    use agpm_dirs::TempDirs;
+
+    #[test]
+    fn records_history() {
+        type TestStore = SqliteStore<TempDirs, Prj>;
+        let dirs = TempDirs::new().unwrap();
+        let path = TestStore::path(&dirs);
+        let mut store = TestStore::open(&path).unwrap();
+        store.add(prj("a")).unwrap();
+        let mut edited = prj("a");
+        edited.dir = "other".into();
+        store.edit("a", edited).unwrap();
+        store.edit("a", prj("b")).unwrap();
+        // A new project with the old name has a history of its own
+        store.add(prj("a")).unwrap();
+        store.remove("b").unwrap();
+        assert!(matches!(
+            store.remove("b"),
+            Err(ProjectStoreError::NonExisting(_))
+        ));
+        let store = TestStore::open(&path).unwrap();
+        assert_eq!(store.history("a").unwrap().len(), 1);
+        let history = store.history("b").unwrap();
+        let actions: Vec<Action> = history.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            vec![Action::Add, Action::Edit, Action::Edit, Action::Remove]
+        );
+        let entry = store.history_entry(history[1].id).unwrap();
+        assert_eq!(entry.project.dir, "other");
+        assert_eq!(entry.project.name, "a");
+    }
+}
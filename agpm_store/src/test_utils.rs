@@ -0,0 +1,51 @@
+//! Helpers shared by the tests of the stores
+
+use crate::{Migration, Versioned};
+use amisgitpm::ProjectIface;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Prj {
+    pub name: String,
+    pub dir: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ProjectIface for Prj {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+    fn get_dir(&self) -> &str {
+        &self.dir
+    }
+    fn get_url(&self) -> &str {
+        ""
+    }
+    fn get_ref_string(&self) -> &str {
+        ""
+    }
+    fn get_install(&self) -> &[String] {
+        &[]
+    }
+    fn get_uninstall(&self) -> &[String] {
+        &[]
+    }
+}
+
+fn add_tags(record: &mut Value) {
+    record["tags"] = serde_json::json!(["migrated"]);
+}
+
+impl Versioned for Prj {
+    const MIGRATIONS: &'static [Migration] = &[add_tags];
+}
+
+pub fn prj(name: &str) -> Prj {
+    Prj {
+        name: name.into(),
+        dir: name.into(),
+        tags: vec![],
+    }
+}
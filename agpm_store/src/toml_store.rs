@@ -0,0 +1,316 @@
+use crate::{ProjectStoreError, Versioned};
+use amisgitpm::{Directories, ProjectIface, ProjectStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::time::Instant;
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+const HEADER: &str = "# The projects managed by agpm.
+#
+# Every [[project]] table is a project. This file can be edited by hand, but the
+# names and directories of the projects must be unique. Comments are kept when
+# agpm writes to this file.
+";
+
+#[derive(Deserialize)]
+struct RawManifest {
+    #[serde(default)]
+    schema_version: usize,
+    #[serde(default)]
+    project: Vec<serde_json::Value>,
+}
+
+/// A struct that implements the [`ProjectStore`](amisgitpm::ProjectStore)
+/// trait keeping all the projects in a single, hand editable, TOML file.
+///
+/// The file is `{projects_db}.toml`. Every `[[project]]` table in it is a
+/// project. When the file is loaded, outdated projects are migrated with the
+/// [`Versioned`] migrations of `T`, after copying the file to
+/// `{projects_db}.toml.backup-v{version}`, and repeated names or directories
+/// are reported, as they may come from editing the file by hand. The
+/// migrations and the edited projects are written over their tables, keeping
+/// the comments of the file.
+pub struct TomlStore<
+    D: Directories,
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+> {
+    path: PathBuf,
+    doc: DocumentMut,
+    projects: Vec<T>,
+    dirs: PhantomData<D>,
+}
+
+impl<D: Directories, T> TomlStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    /// Get the path to the TOML file, given the directories
+    pub fn path(dirs: &D) -> PathBuf {
+        dirs.projects_db().with_extension("toml")
+    }
+    fn open(path: PathBuf) -> Result<Self, ProjectStoreError<D::Error>> {
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let store = Self {
+                path,
+                doc: Self::empty_doc(),
+                projects: vec![],
+                dirs: PhantomData,
+            };
+            store.write()?;
+            return Ok(store);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        let doc: DocumentMut = content.parse()?;
+        let raw: RawManifest = toml_edit::de::from_document(doc.clone())?;
+        let current = T::MIGRATIONS.len();
+        if raw.schema_version > current {
+            return Err(ProjectStoreError::NewerSchema(raw.schema_version, current));
+        }
+        let mut projects = Vec::with_capacity(raw.project.len());
+        for (idx, mut record) in raw.project.into_iter().enumerate() {
+            let name = record
+                .get("name")
+                .and_then(|e| e.as_str())
+                .map_or(format!("#{}", idx + 1), |e| e.to_string());
+            T::MIGRATIONS[raw.schema_version..]
+                .iter()
+                .for_each(|migration| migration(&mut record));
+            projects.push(T::deserialize(record).map_err(|e| ProjectStoreError::Record(name, e))?);
+        }
+        let mut names = HashSet::new();
+        let mut dirs = HashSet::new();
+        for prj in &projects {
+            if !names.insert(prj.get_name()) {
+                return Err(ProjectStoreError::Duplicate(prj.get_name().to_string()));
+            }
+            if !dirs.insert(prj.get_dir()) {
+                return Err(ProjectStoreError::Duplicate(prj.get_dir().to_string()));
+            }
+        }
+        let mut store = Self {
+            path,
+            doc,
+            projects,
+            dirs: PhantomData,
+        };
+        if raw.schema_version < current {
+            let mut backup = store.path.as_os_str().to_owned();
+            backup.push(format!(".backup-v{}", raw.schema_version));
+            std::fs::write(backup, content)?;
+            store.migrate_doc()?;
+            store.write()?;
+        }
+        Ok(store)
+    }
+    fn empty_doc() -> DocumentMut {
+        format!("{HEADER}schema_version = {}\n", T::MIGRATIONS.len())
+            .parse()
+            .unwrap() // The header is valid TOML
+    }
+    fn write(&self) -> Result<(), ProjectStoreError<D::Error>> {
+        let start = Instant::now();
+        std::fs::write(&self.path, self.doc.to_string())?;
+        tracing::debug!(path = ?self.path, elapsed = ?start.elapsed(), "Wrote the toml store");
+        Ok(())
+    }
+    fn projects_table(&mut self) -> Result<&mut ArrayOfTables, ProjectStoreError<D::Error>> {
+        self.doc
+            .entry("project")
+            .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+            .as_array_of_tables_mut()
+            .ok_or(ProjectStoreError::BadManifest)
+    }
+    /// Write the migrated projects over the tables they were read from
+    fn migrate_doc(&mut self) -> Result<(), ProjectStoreError<D::Error>> {
+        self.doc["schema_version"] = toml_edit::value(T::MIGRATIONS.len() as i64);
+        let projects = std::mem::take(&mut self.projects);
+        let tables = self.projects_table()?;
+        for (table, prj) in tables.iter_mut().zip(&projects) {
+            Self::write_table(table, prj)?;
+        }
+        self.projects = projects;
+        Ok(())
+    }
+    /// Write a project over a table, so that the comments of the table and the
+    /// order of its keys are kept
+    fn write_table(table: &mut Table, prj: &T) -> Result<(), ProjectStoreError<D::Error>> {
+        let new_table = toml_edit::ser::to_document(prj)?.into_table();
+        table.retain(|key, _| new_table.contains_key(key));
+        for (key, item) in new_table.iter() {
+            match (table.get_mut(key), item) {
+                (Some(Item::Value(old)), Item::Value(new)) => {
+                    let decor = old.decor().clone();
+                    *old = new.clone();
+                    *old.decor_mut() = decor;
+                }
+                (Some(old), _) => *old = item.clone(),
+                (None, _) => {
+                    table.insert(key, item.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+    fn push_table(&mut self, prj: &T) -> Result<(), ProjectStoreError<D::Error>> {
+        let table = toml_edit::ser::to_document(prj)?.into_table();
+        self.projects_table()?.push(table);
+        Ok(())
+    }
+}
+
+impl<D: Directories, T> ProjectStore<T> for TomlStore<D, T>
+where
+    T: ProjectIface + Serialize + for<'d> Deserialize<'d> + Versioned,
+{
+    type Error = ProjectStoreError<D::Error>;
+    fn new() -> Result<Self, Self::Error> {
+        let dirs = <D as Directories>::new().map_err(Self::Error::Dirs)?;
+        Self::open(Self::path(&dirs))
+    }
+    fn check_name_free(&self, prj_name: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_name() == prj_name)
+    }
+    fn check_dir_free(&self, dir: &str) -> bool {
+        !self.projects.iter().any(|e| e.get_dir() == dir)
+    }
+    fn get_ref(&self, prj_name: &str) -> Option<&T> {
+        self.projects.iter().find(|e| e.get_name() == prj_name)
+    }
+    fn get_clone(&self, prj_name: &str) -> Option<T> {
+        self.get_ref(prj_name).cloned()
+    }
+    fn add(&mut self, prj: T) -> Result<(), Self::Error> {
+        self.push_table(&prj)?;
+        self.projects.push(prj);
+        self.write()
+    }
+    fn remove(&mut self, prj_name: &str) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(prj_name.to_string()))?;
+        let table = self.projects_table()?;
+        let table_idx = table
+            .iter()
+            .position(|e| e.get("name").and_then(|e| e.as_str()) == Some(prj_name))
+            .ok_or(ProjectStoreError::BadManifest)?;
+        table.remove(table_idx);
+        self.projects.remove(idx);
+        self.write()
+    }
+    fn edit(&mut self, old_prj_name: &str, new_prj: T) -> Result<(), Self::Error> {
+        let idx = self
+            .projects
+            .iter()
+            .position(|e| e.get_name() == old_prj_name)
+            .ok_or_else(|| ProjectStoreError::NonExisting(old_prj_name.to_string()))?;
+        let table = self
+            .projects_table()?
+            .iter_mut()
+            .find(|e| e.get("name").and_then(|e| e.as_str()) == Some(old_prj_name))
+            .ok_or(ProjectStoreError::BadManifest)?;
+        Self::write_table(table, &new_prj)?;
+        self.projects[idx] = new_prj;
+        self.write()
+    }
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
+        Box::new(self.projects.iter())
+    }
+    fn is_empty(&self) -> bool {
+        self.projects.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{prj, Prj};
+    use agpm_dirs::TempDirs;
+
+    #[test]
+    fn keeps_comments_and_rejects_duplicates() {
+        type TestStore = TomlStore<TempDirs, Prj>;
+        let dirs = TempDirs::new().unwrap();
+        let path = TestStore::path(&dirs);
+        let mut store = TestStore::open(path.clone()).unwrap();
+        store.add(prj("a")).unwrap();
+        store.add(prj("b")).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(
+            &path,
+            content
+                .replace("\n[[project]]", "\n# mine\n[[project]]")
+                .replace("name = \"a\"", "name = \"a\" # first"),
+        )
+        .unwrap();
+        let mut store = TestStore::open(path.clone()).unwrap();
+        assert!(store.get_ref("b").is_some());
+        // Editing a project writes over its table, where it was
+        let edited = Prj {
+            tags: vec!["edited".into()],
+            ..prj("a2")
+        };
+        store.edit("a", edited).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# mine\n[[project]]\nname = \"a2\" # first"));
+        assert!(content.find("\"a2\"") < content.find("\"b\""));
+        assert!(
+            TestStore::open(path.clone())
+                .unwrap()
+                .get_ref("a2")
+                .unwrap()
+                .tags
+                == ["edited"]
+        );
+        store.remove("b").unwrap();
+        assert!(matches!(
+            store.remove("b"),
+            Err(ProjectStoreError::NonExisting(_))
+        ));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# mine"));
+        assert!(!content.contains("\"b\""));
+        std::fs::write(
+            &path,
+            format!("{content}\n[[project]]\nname = \"c\"\ndir = \"a2\"\n"),
+        )
+        .unwrap();
+        assert!(matches!(
+            TestStore::open(path),
+            Err(ProjectStoreError::Duplicate(dir)) if dir == "a2"
+        ));
+    }
+
+    #[test]
+    fn migrates_keeping_comments() {
+        type TestStore = TomlStore<TempDirs, Prj>;
+        let dirs = TempDirs::new().unwrap();
+        let path = TestStore::path(&dirs);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let old = "# mine
+schema_version = 0
+
+# the first one
+[[project]]
+name = \"a\" # its name
+dir = \"a\"
+";
+        std::fs::write(&path, old).unwrap();
+        let store = TestStore::open(path.clone()).unwrap();
+        assert_eq!(store.get_ref("a").unwrap().tags, vec!["migrated"]);
+        let content = std::fs::read_to_string(&path).unwrap();
+        for kept in ["# mine", "# the first one", "name = \"a\" # its name"] {
+            assert!(content.contains(kept), "{kept:?} isn't in {content}");
+        }
+        assert!(content.contains("schema_version = 1"));
+        let mut backup = path.into_os_string();
+        backup.push(".backup-v0");
+        assert_eq!(std::fs::read_to_string(backup).unwrap(), old);
+    }
+}
@@ -1,23 +1,58 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+use agpm_config::{Config, ConfigError};
 use amisgitpm::Directories;
 use glob::{GlobError, PatternError};
 use json_tables::{Deserialize, Serialize, Table, TableBuilderError, TableError};
 use regex::Regex;
 use reqwest::blocking;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use thiserror::Error;
+use tracing::{debug, info};
 
-const REGISTRY: [&str; 6] = [
-    "bash.json",
-    "cargo.json",
-    "meson.json",
-    "cmain.json",
-    "cmake.json",
-    "makefile.json",
+/// The files of the suggestions, with the content that is bundled with the
+/// crate, to be unpacked when they can't be downloaded
+const REGISTRY: [(&str, &str); 6] = [
+    ("bash.json", include_str!("../suggestions/bash.json")),
+    ("cargo.json", include_str!("../suggestions/cargo.json")),
+    ("meson.json", include_str!("../suggestions/meson.json")),
+    ("cmain.json", include_str!("../suggestions/cmain.json")),
+    ("cmake.json", include_str!("../suggestions/cmake.json")),
+    (
+        "makefile.json",
+        include_str!("../suggestions/makefile.json"),
+    ),
 ];
 
+/// Where the suggestions are downloaded from, by default
+const SOURCE: &str =
+    "https://raw.githubusercontent.com/david-soto-m/amisgitpm/main/agpm_suggestions/suggestions/";
+
+/// The `suggestions` object of the configuration file, for example
+/// `{"suggestions": {"source": "/usr/share/agpm/suggestions"}}`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SuggestionsConfig {
+    /// An url or a local directory with the files of the suggestions
+    pub source: String,
+}
+
+impl Default for SuggestionsConfig {
+    fn default() -> Self {
+        Self {
+            source: SOURCE.into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    suggestions: SuggestionsConfig,
+}
+
 /// A trait to get a suggestions directory for the DB
 pub trait SuggestionsDirs: Directories {
     /// Get the suggestions directory
@@ -30,6 +65,10 @@ pub trait SuggestionsDirs: Directories {
 struct SuggestionsItem {
     /// File names to detect in order to make the suggestions contained bellow
     pub file_types: Vec<String>,
+    /// Executables that are needed to follow the suggestions, like `cargo` or
+    /// `meson>=0.60`
+    #[serde(default)]
+    pub requirements: Vec<String>,
     /// The set of suggestions in order to build and install a project
     pub install_suggestions: Vec<Vec<String>>,
     /// The set of suggestion in order to uninstall a project
@@ -47,7 +86,8 @@ fn get_build_suggestions(readme_file: &PathBuf) -> Result<Vec<Vec<String>>, Sugg
 
 type InstallSugs = Vec<Vec<String>>;
 type UninstallSugs = Vec<Vec<String>>;
-type Suggestions = (InstallSugs, UninstallSugs);
+type Requirements = Vec<String>;
+type Suggestions = (InstallSugs, UninstallSugs, Requirements);
 
 /// Get the suggestions for a given path to a project directory
 ///
@@ -55,7 +95,8 @@ type Suggestions = (InstallSugs, UninstallSugs);
 /// `r"((?i)compil|instal|build)"` in any of their headings
 ///
 /// They will also be examined for conformities with the any known structures in the suggestions db
-/// such as being a cargo project or having a Makefile.
+/// such as being a cargo project or having a Makefile. The requirements of all the
+/// conforming structures are returned without repetitions.
 ///
 /// # Errors
 /// - Can't convert a path to a utf8 encoded str
@@ -88,46 +129,93 @@ pub fn get_suggestions<P: SuggestionsDirs>(
                     .iter()
                     .flat_map(|e| e.uninstall_suggestions.clone())
                     .collect(),
+                db_sug
+                    .iter()
+                    .flat_map(|e| e.requirements.clone())
+                    .fold(vec![], |mut acc, req| {
+                        if !acc.contains(&req) {
+                            acc.push(req);
+                        }
+                        acc
+                    }),
             ))
         }
-        Err(_) => Ok((readme, vec![])),
+        Err(_) => Ok((readme, vec![], vec![])),
     }
 }
 
 /// Downloads all elements in the REGISTRY and stores them.
 ///
-/// It either creates a new table at the `SuggestionsDir` or reads from an existing
-/// table, and writes into it.
-/// # Panics
-/// Not really, but it does have an unwrap where if there is a `REGISTRY` file that
-/// doesn't have .json it would panic, but there isn't.
+/// They are downloaded from the `source` of the [`SuggestionsConfig`], which can
+/// also be a local directory. It either creates a new table at the
+/// `SuggestionsDir` or reads from an existing table, and writes into it.
 pub fn download_resources<P: SuggestionsDirs>() -> Result<(), SuggestionsError> {
-    let f_str = "https://raw.githubusercontent.com/david-soto-m/amisgitpm/main/agpm_suggestions/suggestions/";
-    let dir = P::new()
-        .map_err(|e| SuggestionsError::DirsError(e.to_string()))?
-        .suggestions();
+    let dirs = P::new().map_err(|e| SuggestionsError::DirsError(e.to_string()))?;
+    let source = Config::load(&dirs.config())?
+        .get::<ConfigFile>()?
+        .suggestions
+        .source;
+    let start = Instant::now();
+    let mut table = open_table(&dirs.suggestions())?;
+    for (file, _) in REGISTRY {
+        let file_start = Instant::now();
+        let item: SuggestionsItem =
+            if source.starts_with("http://") || source.starts_with("https://") {
+                let url = format!("{}/{file}", source.trim_end_matches('/'));
+                blocking::get(url)?.json()?
+            } else {
+                serde_json::from_str(&std::fs::read_to_string(Path::new(&source).join(file))?)?
+            };
+        debug!(file, elapsed = ?file_start.elapsed(), "Got the suggestions");
+        set_item(&mut table, file, item)?;
+    }
+    info!(source, elapsed = ?start.elapsed(), "Updated the suggestions");
+    Ok(())
+}
 
-    let mut table = match Table::<SuggestionsItem>::builder(&dir)
+/// Write the suggestions that are bundled with this crate to the table at
+/// `dir`, substituting those already present. They are used when the
+/// suggestions can't be downloaded, like without a connection
+pub fn unpack_resources(dir: &Path) -> Result<(), SuggestionsError> {
+    let mut table = open_table(dir)?;
+    for (file, content) in REGISTRY {
+        set_item(&mut table, file, serde_json::from_str(content)?)?;
+    }
+    info!(?dir, "Unpacked the bundled suggestions");
+    Ok(())
+}
+
+/// Open the table of the suggestions at `dir` to write to it, creating it if
+/// it doesn't exist
+fn open_table(dir: &Path) -> Result<Table<SuggestionsItem>, SuggestionsError> {
+    match Table::<SuggestionsItem>::builder(dir)
         .set_auto_write()
         .build()
     {
-        Ok(table) => table,
+        Ok(table) => Ok(table),
         Err(TableBuilderError::TableAlreadyExistsError) => {
-            Table::builder(&dir).set_auto_write().load()?
+            Ok(Table::builder(dir).set_auto_write().load()?)
         }
-        Err(a) => return Err(a.into()),
-    };
-    for file in REGISTRY {
-        let (name, _) = file.rsplit_once('.').unwrap(); // Guaranteed by me that this doesn't panic
-        let url = format!("{f_str}{file}");
-        let item: SuggestionsItem = blocking::get(url)?.json()?;
-        match table.get_mut_element(name) {
-            Some(el) => el.info = item,
-            None => {
-                table.push(name, item)?;
-            }
-        };
+        Err(a) => Err(a.into()),
     }
+}
+
+/// Set the suggestions of a file of the `REGISTRY` in the table
+/// # Panics
+/// Not really, it unwraps the extension of the file, and all the files of the
+/// `REGISTRY` have one
+fn set_item(
+    table: &mut Table<SuggestionsItem>,
+    file: &str,
+    item: SuggestionsItem,
+) -> Result<(), SuggestionsError> {
+    let (name, _) = file.rsplit_once('.').unwrap(); // Guaranteed by me that this doesn't panic
+    match table.get_mut_element(name) {
+        Some(el) => el.info = item,
+        None => {
+            table.push(name, item)?;
+        }
+    };
     Ok(())
 }
 
@@ -185,6 +273,12 @@ pub enum SuggestionsError {
     /// re-implementing the BuildSuggestions
     #[error("{0}")]
     DirsError(String),
+    /// The configuration file can't be read
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// A suggestion of a local source isn't valid
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 #[cfg(test)]
@@ -196,11 +290,36 @@ mod tests {
         let db_loc = Path::new("suggestions");
         let table = SuggestionsTable::new(db_loc).unwrap();
         let len = table
-            .get_suggestions(&Path::new("../tests/projects/mess_project"))
+            .get_suggestions(Path::new("../tests/projects/mess_project"))
             .len();
         assert_eq!(len, 3);
     }
     #[test]
+    fn suggests_requirements() {
+        let db_loc = Path::new("suggestions");
+        let table = SuggestionsTable::new(db_loc).unwrap();
+        let reqs: Vec<String> = table
+            .get_suggestions(Path::new("../tests/projects/mess_project"))
+            .iter()
+            .flat_map(|e| e.requirements.clone())
+            .collect();
+        assert!(reqs.contains(&"make".to_string()));
+    }
+    #[test]
+    fn unpacks_the_bundled_suggestions() {
+        let dir = std::env::temp_dir().join(format!("agpm-suggestions-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        super::unpack_resources(&dir).unwrap();
+        // Unpacking again substitutes them
+        super::unpack_resources(&dir).unwrap();
+        let table = SuggestionsTable::new(&dir).unwrap();
+        assert_eq!(
+            table.table.get_table_content().count(),
+            super::REGISTRY.len()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
     fn all_build_aux_json_is_correct() {
         let db_loc = Path::new("suggestions");
         SuggestionsTable::new(db_loc).unwrap();
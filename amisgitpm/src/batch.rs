@@ -0,0 +1,135 @@
+//! Results of operations that are applied to many projects at once, and that
+//! go on after some of them fail.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Apply `f` to every item, with up to `jobs` threads at once, and get the
+/// results in the order of the items. With one job, or a single item, no
+/// threads are started
+pub fn parallel_map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        return items.iter().map(f).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<(usize, R)>> = Mutex::new(Vec::with_capacity(items.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(idx) else {
+                    break;
+                };
+                let result = f(item);
+                results
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push((idx, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    results.sort_by_key(|(idx, _)| *idx);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// What happened to one of the projects of a batch operation
+#[derive(Debug)]
+pub enum Outcome<E> {
+    /// The operation was done
+    Done,
+    /// The operation wasn't attempted. Holds the reason
+    Skipped(String),
+    /// The operation failed
+    Failed(E),
+}
+
+impl<E> Outcome<E> {
+    /// Whether the operation failed
+    pub fn is_failed(&self) -> bool {
+        matches!(self, Self::Failed(_))
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for Outcome<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Done => write!(f, "done"),
+            Self::Skipped(reason) => write!(f, "skipped, {reason}"),
+            Self::Failed(e) => write!(f, "failed, {e}"),
+        }
+    }
+}
+
+/// The outcome of a batch operation for each of the projects, in the order in
+/// which they were handled
+#[derive(Debug)]
+pub struct BatchReport<E> {
+    /// The project names with what happened to them
+    pub outcomes: Vec<(String, Outcome<E>)>,
+    /// The projects that were only built again, as a project they depend on
+    /// changed. Their outcome is the outcome of building them
+    pub rebuilt: Vec<String>,
+}
+
+impl<E> Default for BatchReport<E> {
+    fn default() -> Self {
+        Self {
+            outcomes: vec![],
+            rebuilt: vec![],
+        }
+    }
+}
+
+impl<E> BatchReport<E> {
+    /// Record what happened to a project
+    pub fn push<T: AsRef<str>>(&mut self, prj_name: T, outcome: Outcome<E>) {
+        self.outcomes.push((prj_name.as_ref().to_string(), outcome));
+    }
+    /// Whether the project was only built again, see [`Self::rebuilt`]
+    pub fn was_rebuilt(&self, prj_name: &str) -> bool {
+        self.rebuilt.iter().any(|e| e == prj_name)
+    }
+    /// Get the names of the projects for which the operation failed
+    pub fn failed(&self) -> Vec<&str> {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| outcome.is_failed())
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+    /// Whether the operation failed for any of the projects
+    pub fn has_failures(&self) -> bool {
+        self.outcomes.iter().any(|(_, outcome)| outcome.is_failed())
+    }
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for BatchReport<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, outcome) in &self.outcomes {
+            writeln!(f, "{name}: {outcome}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_in_order_with_any_jobs() {
+        let items: Vec<usize> = (0..20).collect();
+        for jobs in [0, 1, 3, 50] {
+            let doubled = parallel_map(&items, jobs, |e| e * 2);
+            assert_eq!(doubled, items.iter().map(|e| e * 2).collect::<Vec<_>>());
+        }
+        assert!(parallel_map(&[] as &[usize], 4, |e| *e).is_empty());
+    }
+}
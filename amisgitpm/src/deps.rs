@@ -0,0 +1,223 @@
+//! Helpers to order projects according to the dependencies they declare with
+//! [`ProjectIface::get_dependencies`].
+//!
+//! Dependencies that are not part of the set that is being sorted are ignored,
+//! as they are supposed to be satisfied somewhere else (typically they are
+//! already installed).
+
+use crate::{CommonPMErrors, ProjectIface};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Unvisited,
+    Visiting,
+    Done,
+}
+
+struct Sorter<'a, 'b, P: ProjectIface> {
+    prjs: &'b [&'a P],
+    index: HashMap<&'a str, usize>,
+    marks: Vec<Mark>,
+    path: Vec<usize>,
+    order: Vec<&'a P>,
+}
+
+impl<'a, 'b, P: ProjectIface> Sorter<'a, 'b, P> {
+    fn visit(&mut self, idx: usize) -> Result<(), CommonPMErrors> {
+        match self.marks[idx] {
+            Mark::Done => return Ok(()),
+            Mark::Visiting => {
+                let start = self.path.iter().position(|&e| e == idx).unwrap_or(0);
+                let mut cycle: Vec<String> = self.path[start..]
+                    .iter()
+                    .map(|&e| self.prjs[e].get_name().to_string())
+                    .collect();
+                cycle.push(self.prjs[idx].get_name().to_string());
+                Err(CommonPMErrors::DependencyCycle(cycle))?;
+            }
+            Mark::Unvisited => {}
+        }
+        self.marks[idx] = Mark::Visiting;
+        self.path.push(idx);
+        let prj = self.prjs[idx];
+        for dep in prj.get_dependencies() {
+            if let Some(&dep_idx) = self.index.get(dep.as_str()) {
+                self.visit(dep_idx)?;
+            }
+        }
+        self.path.pop();
+        self.marks[idx] = Mark::Done;
+        self.order.push(prj);
+        Ok(())
+    }
+}
+
+/// Sort the projects so that every project comes after all of its dependencies.
+/// Projects that don't depend on each other keep their relative order.
+/// # Errors
+/// - If the dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+pub fn topological_order<'a, P: ProjectIface>(
+    prjs: &[&'a P],
+) -> Result<Vec<&'a P>, CommonPMErrors> {
+    let mut sorter = Sorter {
+        prjs,
+        index: prjs
+            .iter()
+            .enumerate()
+            .map(|(idx, prj)| (prj.get_name(), idx))
+            .collect(),
+        marks: vec![Mark::Unvisited; prjs.len()],
+        path: vec![],
+        order: Vec::with_capacity(prjs.len()),
+    };
+    for idx in 0..prjs.len() {
+        sorter.visit(idx)?;
+    }
+    Ok(sorter.order)
+}
+
+/// Group the projects in levels, so that every project is in a later level
+/// than all of its dependencies. The projects of a level don't depend on each
+/// other, so they can be built at the same time. Within a level, projects keep
+/// their topological order.
+/// # Errors
+/// - If the dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+pub fn levels<'a, P: ProjectIface>(prjs: &[&'a P]) -> Result<Vec<Vec<&'a P>>, CommonPMErrors> {
+    let mut level_of: HashMap<&str, usize> = HashMap::new();
+    let mut levels: Vec<Vec<&'a P>> = vec![];
+    for prj in topological_order(prjs)? {
+        // In topological order the dependencies already have a level
+        let level = prj
+            .get_dependencies()
+            .iter()
+            .filter_map(|dep| level_of.get(dep.as_str()))
+            .map(|level| level + 1)
+            .max()
+            .unwrap_or(0);
+        level_of.insert(prj.get_name(), level);
+        if levels.len() <= level {
+            levels.push(vec![]);
+        }
+        levels[level].push(prj);
+    }
+    Ok(levels)
+}
+
+/// Get all the projects that depend, directly or transitively, on the project
+/// with name `prj_name`. They are returned in an order in which they can be built.
+/// # Errors
+/// - If the dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+pub fn dependents<'a, P: ProjectIface>(
+    prjs: &[&'a P],
+    prj_name: &str,
+) -> Result<Vec<&'a P>, CommonPMErrors> {
+    let mut affected: HashSet<&str> = HashSet::from([prj_name]);
+    let order = topological_order(prjs)?;
+    // In topological order all dependencies of a project are visited before it
+    Ok(order
+        .into_iter()
+        .filter(|prj| {
+            if prj.get_name() != prj_name
+                && prj
+                    .get_dependencies()
+                    .iter()
+                    .any(|dep| affected.contains(dep.as_str()))
+            {
+                affected.insert(prj.get_name());
+                true
+            } else {
+                false
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Prj(String, Vec<String>);
+
+    impl ProjectIface for Prj {
+        fn get_name(&self) -> &str {
+            &self.0
+        }
+        fn get_dir(&self) -> &str {
+            &self.0
+        }
+        fn get_url(&self) -> &str {
+            ""
+        }
+        fn get_ref_string(&self) -> &str {
+            ""
+        }
+        fn get_install(&self) -> &[String] {
+            &[]
+        }
+        fn get_uninstall(&self) -> &[String] {
+            &[]
+        }
+        fn get_dependencies(&self) -> &[String] {
+            &self.1
+        }
+    }
+
+    fn prj(name: &str, deps: &[&str]) -> Prj {
+        Prj(name.into(), deps.iter().map(|e| e.to_string()).collect())
+    }
+
+    fn names(prjs: &[&Prj]) -> Vec<String> {
+        prjs.iter().map(|e| e.0.clone()).collect()
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let (a, b, c, d) = (
+            prj("a", &["b", "c"]),
+            prj("b", &["c"]),
+            prj("c", &["not_in_set"]),
+            prj("d", &[]),
+        );
+        let order = topological_order(&[&a, &b, &c, &d]).unwrap();
+        assert_eq!(names(&order), vec!["c", "b", "a", "d"]);
+    }
+
+    #[test]
+    fn groups_in_levels() {
+        let (a, b, c, d, e) = (
+            prj("a", &["b", "c"]),
+            prj("b", &["c"]),
+            prj("c", &["not_in_set"]),
+            prj("d", &[]),
+            prj("e", &["c"]),
+        );
+        let levels = levels(&[&a, &b, &c, &d, &e]).unwrap();
+        let levels: Vec<_> = levels.iter().map(|level| names(level)).collect();
+        assert_eq!(levels, vec![vec!["c", "d"], vec!["b", "e"], vec!["a"]]);
+    }
+
+    #[test]
+    fn detects_cycles() {
+        let (a, b, c) = (prj("a", &["b"]), prj("b", &["c"]), prj("c", &["a"]));
+        match topological_order(&[&a, &b, &c]) {
+            Err(CommonPMErrors::DependencyCycle(cycle)) => {
+                assert_eq!(cycle, vec!["a", "b", "c", "a"])
+            }
+            _ => panic!("The cycle went undetected"),
+        }
+    }
+
+    #[test]
+    fn finds_transitive_dependents() {
+        let (a, b, c, d) = (
+            prj("a", &["b"]),
+            prj("b", &["c"]),
+            prj("c", &[]),
+            prj("d", &[]),
+        );
+        let deps = dependents(&[&a, &b, &c, &d], "c").unwrap();
+        assert_eq!(names(&deps), vec!["b", "a"]);
+    }
+}
@@ -21,4 +21,9 @@ where
     fn git(&self) -> PathBuf;
     /// Where to store old copies of the projects
     fn old(&self) -> PathBuf;
+    /// Where to look for the configuration file. By default it is a `config.json`
+    /// file next to the projects db
+    fn config(&self) -> PathBuf {
+        self.projects_db().with_file_name("config.json")
+    }
 }
@@ -0,0 +1,113 @@
+//! The inconsistencies that can appear between the store of a project manager
+//! and its directories, like after an interrupted operation or editing the
+//! store by hand. They are found with
+//! [`PMProgrammatic::diagnose`](crate::PMProgrammatic::diagnose) and repaired
+//! with [`PMProgrammatic::repair`](crate::PMProgrammatic::repair).
+
+use git2::Repository;
+use std::path::Path;
+
+/// Something that doesn't match between the store and the directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// A directory of `git()` was left by an interrupted operation. Holds the
+    /// directory
+    LeftoverGit(String),
+    /// Several projects use the same directory. Holds the directory, the
+    /// project whose sources are in it, and the rest of the projects
+    DuplicateDir(String, String, Vec<String>),
+    /// A project of the store has no sources. Holds the name of the project
+    MissingSources(String),
+    /// The reference of a project doesn't resolve in its sources, or they
+    /// aren't a repository. Holds the name of the project and the reference
+    UnresolvedRef(String, String),
+    /// A directory of `src()` belongs to no project. Holds the directory
+    OrphanSources(String),
+    /// A directory of `old()` belongs to no project. Holds the directory
+    OrphanOld(String),
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LeftoverGit(dir) => {
+                write!(
+                    f,
+                    "The directory {dir} was left by an interrupted operation"
+                )
+            }
+            Self::DuplicateDir(dir, kept, rest) => {
+                write!(f, "The projects {rest:?} use the directory {dir} of {kept}")
+            }
+            Self::MissingSources(name) => write!(f, "The project {name} has no sources"),
+            Self::UnresolvedRef(name, refe) => {
+                write!(
+                    f,
+                    "The reference {refe} of {name} doesn't resolve in its sources"
+                )
+            }
+            Self::OrphanSources(dir) => write!(f, "The sources in {dir} belong to no project"),
+            Self::OrphanOld(dir) => {
+                write!(f, "The previous version in {dir} belongs to no project")
+            }
+        }
+    }
+}
+
+impl Finding {
+    /// What [`PMProgrammatic::repair`](crate::PMProgrammatic::repair) does
+    /// about the finding
+    pub fn fix(&self) -> &'static str {
+        match self {
+            Self::LeftoverGit(_) | Self::OrphanSources(_) | Self::OrphanOld(_) => {
+                "Remove the directory"
+            }
+            Self::DuplicateDir(..) => {
+                "Take the rest of the projects out of the store, to install them again in another directory"
+            }
+            Self::MissingSources(_) => "Get the sources again and build them",
+            Self::UnresolvedRef(..) => {
+                "Get the sources again and build them, keeping the current ones as the previous version"
+            }
+        }
+    }
+}
+
+/// The names of the entries of a directory. A missing directory has none, and
+/// entries whose name isn't unicode are left out
+pub(crate) fn entry_names(path: &Path) -> std::io::Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut names = vec![];
+    for entry in std::fs::read_dir(path)? {
+        if let Some(name) = entry?.file_name().to_str() {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Remove a directory or a file
+pub(crate) fn remove(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// The url of the first remote of a repository
+pub(crate) fn remote_url(repo: &Repository) -> Option<String> {
+    let remotes = repo.remotes().ok()?;
+    let remote = repo.find_remote(remotes.get(0)?).ok()?;
+    remote.url().map(str::to_string)
+}
+
+/// Whether a reference resolves in the repository at `path`
+pub(crate) fn resolves(path: &Path, ref_string: &str) -> bool {
+    Repository::open(path)
+        .and_then(|repo| repo.revparse_single(ref_string).map(|_| ()))
+        .is_ok()
+}
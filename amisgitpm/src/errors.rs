@@ -0,0 +1,356 @@
+//! The errors that every project manager shares, with what to do about them.
+//!
+//! Each error belongs to an [`ErrorClass`], that front ends can turn into an
+//! exit code for scripts. The codes are
+//!
+//! |Code|Class|
+//! |-|-|
+//! |0 |Success|
+//! |1 |[`ErrorClass::Other`]|
+//! |2 |Invalid arguments, left for the front end|
+//! |3 |[`ErrorClass::NotFound`]|
+//! |4 |[`ErrorClass::Conflict`]|
+//! |5 |[`ErrorClass::Requirements`]|
+//! |6 |[`ErrorClass::Git`]|
+//! |7 |[`ErrorClass::Script`]|
+//! |8 |[`ErrorClass::Filesystem`]|
+//! |9 |[`ErrorClass::Store`]|
+//! |10|[`ErrorClass::Partial`]|
+
+use std::path::{Path, PathBuf};
+
+/// An error class that's needed to provide methods
+#[non_exhaustive]
+#[derive(Debug, Clone)]
+pub enum CommonPMErrors {
+    /// Attempting to install an already existing project. Holds the name or
+    /// the directory that is taken
+    AlreadyExisting(String),
+    /// Attempting to get a non existing project from the store. Holds the
+    /// project name
+    NonExisting(String),
+    /// Couldn't parse an OsStr as a utf-8 str
+    Os2Str,
+    /// Can't find a reference to detached head
+    BadRef,
+    /// Couldn't update with a fast forward. Holds the project name
+    ImposibleUpdate(String),
+    /// A project depends on projects that aren't installed. Holds the project
+    /// name and the missing dependencies
+    MissingDependencies(String, Vec<String>),
+    /// Projects can't be ordered because they depend on each other. Holds the
+    /// names of the projects that form the cycle
+    DependencyCycle(Vec<String>),
+    /// Attempting to uninstall a project that others depend on. Holds the
+    /// project name and the names of the dependent projects
+    HasDependents(String, Vec<String>),
+    /// The host lacks executables that are needed to build a project. Holds the
+    /// project name and the description of each unmet requirement
+    MissingRequirements(String, Vec<String>),
+    /// The commit a project is locked to isn't in its repository. Holds the
+    /// project name and the commit
+    MissingCommit(String, String),
+    /// Attempting to restore a project without previous versions. Holds the
+    /// project name
+    NoPreviousVersion(String),
+    /// The sources of an installed project aren't in their directory. Holds
+    /// the project name
+    MissingSources(String),
+}
+impl std::error::Error for CommonPMErrors {}
+impl std::fmt::Display for CommonPMErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AlreadyExisting(taken) => {
+                write!(
+                    f,
+                    "A project with the name or directory {taken} already exists"
+                )
+            }
+            Self::NonExisting(prj) => write!(f, "There is no project named {prj}"),
+            Self::Os2Str => write!(f, "Couldn't convert from &Osstr to utf-8 &str"),
+            Self::BadRef => write!(f, "Couldn't find a reference to a non detached HEAD"),
+            Self::ImposibleUpdate(prj) => {
+                write!(
+                    f,
+                    "The update of {prj} couldn't be solved by a fast forward."
+                )
+            }
+            Self::MissingDependencies(prj, deps) => {
+                write!(
+                    f,
+                    "{prj} depends on projects that aren't installed: {deps:?}"
+                )
+            }
+            Self::DependencyCycle(cycle) => {
+                write!(f, "There is a dependency cycle: {}", cycle.join(" -> "))
+            }
+            Self::HasDependents(prj, deps) => {
+                write!(f, "Other projects depend on {prj}: {deps:?}")
+            }
+            Self::MissingRequirements(prj, reqs) => {
+                write!(f, "{prj} can't be built, missing: {}", reqs.join(", "))
+            }
+            Self::MissingCommit(prj, commit) => {
+                write!(
+                    f,
+                    "The commit {commit} of {prj} can't be found in its repository"
+                )
+            }
+            Self::NoPreviousVersion(prj) => {
+                write!(f, "{prj} has no previous version to go back to")
+            }
+            Self::MissingSources(prj) => write!(f, "The sources of {prj} are missing"),
+        }
+    }
+}
+
+impl CommonPMErrors {
+    /// What can be done to solve the error
+    pub fn hint(&self) -> String {
+        match self {
+            Self::AlreadyExisting(taken) => {
+                format!("Choose another name or directory than {taken}, or uninstall the project that has it")
+            }
+            Self::NonExisting(prj) => {
+                format!("Check the name {prj:?} against the installed projects")
+            }
+            Self::Os2Str => "Use directories whose paths are valid unicode".into(),
+            Self::BadRef => {
+                "Set the reference of the project to a branch, like `refs/heads/main`".into()
+            }
+            Self::ImposibleUpdate(prj) => format!(
+                "The history of {prj} was rewritten upstream. Install it again to get the new one"
+            ),
+            Self::MissingDependencies(prj, deps) => {
+                format!(
+                    "Install {} before {prj}, or install them together with it",
+                    deps.join(", ")
+                )
+            }
+            Self::DependencyCycle(_) => {
+                "Remove one of the dependencies of the cycle from its project".into()
+            }
+            Self::HasDependents(prj, deps) => {
+                format!(
+                    "Uninstall {} first, or together with {prj}, or remove {prj} from their dependencies",
+                    deps.join(", ")
+                )
+            }
+            Self::MissingRequirements(..) => "Install the missing programs and try again".into(),
+            Self::MissingCommit(prj, commit) => format!(
+                "Lock {prj} again, or check that the commit {commit} is still in its repository"
+            ),
+            Self::NoPreviousVersion(prj) => {
+                format!("Every previous version of {prj} was already restored. Keep more of them to go further back")
+            }
+            Self::MissingSources(prj) => {
+                format!("Get the sources of {prj} again by reinstalling it")
+            }
+        }
+    }
+    /// The class of the error
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::NonExisting(_) | Self::NoPreviousVersion(_) | Self::MissingSources(_) => {
+                ErrorClass::NotFound
+            }
+            Self::AlreadyExisting(_)
+            | Self::MissingDependencies(..)
+            | Self::DependencyCycle(_)
+            | Self::HasDependents(..) => ErrorClass::Conflict,
+            Self::MissingRequirements(..) => ErrorClass::Requirements,
+            Self::BadRef | Self::ImposibleUpdate(_) | Self::MissingCommit(..) => ErrorClass::Git,
+            Self::Os2Str => ErrorClass::Filesystem,
+        }
+    }
+}
+
+/// An error with a file or a directory, holding what was being done with it
+#[derive(Debug)]
+pub struct PathError {
+    /// What was being done, like `remove` or `move`
+    pub operation: &'static str,
+    /// The file or directory
+    pub path: PathBuf,
+    /// The error of the operation
+    pub source: std::io::Error,
+}
+impl std::error::Error for PathError {}
+impl std::fmt::Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unable to {} {:?}: {}",
+            self.operation, self.path, self.source
+        )
+    }
+}
+
+impl PathError {
+    /// What can be done to solve the error
+    pub fn hint(&self) -> String {
+        match self.source.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                format!("Check the permissions of {:?} and try again", self.path)
+            }
+            std::io::ErrorKind::NotFound => format!(
+                "{:?} was moved or removed while it was being used. Check the directories of the projects",
+                self.path
+            ),
+            _ => format!(
+                "Check that {:?} can be used, and that there is space left in the disk",
+                self.path
+            ),
+        }
+    }
+}
+
+/// Add the path that an io operation was done with to its error
+pub trait PathContext<T> {
+    /// Turn the error into a [`PathError`] for `operation` with `path`
+    fn at<P: AsRef<Path>>(self, operation: &'static str, path: P) -> Result<T, PathError>;
+}
+
+impl<T> PathContext<T> for std::io::Result<T> {
+    fn at<P: AsRef<Path>>(self, operation: &'static str, path: P) -> Result<T, PathError> {
+        self.map_err(|source| PathError {
+            operation,
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// An error of a git operation with the repository of a project
+#[derive(Debug)]
+pub struct GitError {
+    /// The name of the project
+    pub project: String,
+    /// The error of the operation
+    pub source: git2::Error,
+}
+impl std::error::Error for GitError {}
+impl std::fmt::Display for GitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Git failed with the repository of {}: {}",
+            self.project,
+            self.source.message()
+        )
+    }
+}
+
+impl GitError {
+    /// What can be done to solve the error
+    pub fn hint(&self) -> String {
+        let prj = &self.project;
+        match (self.source.class(), self.source.code()) {
+            (git2::ErrorClass::Net | git2::ErrorClass::Http | git2::ErrorClass::Ssh, _) => {
+                format!("Check the connection to the network and the url of {prj}")
+            }
+            (git2::ErrorClass::Repository | git2::ErrorClass::Os, git2::ErrorCode::NotFound) => {
+                format!(
+                    "The sources of {prj} aren't a valid repository, get them again by reinstalling it"
+                )
+            }
+            (git2::ErrorClass::Reference, _) => {
+                format!("Check that the reference of {prj} exists in its repository")
+            }
+            _ => format!("Check the url and the reference of {prj}"),
+        }
+    }
+}
+
+/// Add the project whose repository a git operation was done with to its error
+pub trait GitContext<T> {
+    /// Turn the error into a [`GitError`] for the project `prj_name`
+    fn of(self, prj_name: &str) -> Result<T, GitError>;
+}
+
+impl<T> GitContext<T> for Result<T, git2::Error> {
+    fn of(self, prj_name: &str) -> Result<T, GitError> {
+        self.map_err(|source| GitError {
+            project: prj_name.to_string(),
+            source,
+        })
+    }
+}
+
+/// Errors of the parts that a project manager is made of, like its store or
+/// its interactions, that know what can be done to solve them
+pub trait Hint {
+    /// What can be done to solve the error, if anything is known
+    fn hint(&self) -> Option<String>;
+}
+
+/// The kinds of errors, for front ends to tell them apart, like with the exit
+/// codes of the [module](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Any other error
+    Other,
+    /// A project, or a previous version of it, isn't there
+    NotFound,
+    /// The operation conflicts with the installed projects, like a taken name
+    /// or a project that others depend on
+    Conflict,
+    /// The host lacks programs that are needed
+    Requirements,
+    /// Git operations failed, like fetching or finding a reference
+    Git,
+    /// A script failed, couldn't be started or took too long
+    Script,
+    /// Files or directories couldn't be read, written or removed
+    Filesystem,
+    /// The store, the configuration or the directories couldn't be used
+    Store,
+    /// Some projects of a batch operation failed, and the rest were done
+    Partial,
+}
+
+impl ErrorClass {
+    /// The exit code for errors of the class
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::NotFound => 3,
+            Self::Conflict => 4,
+            Self::Requirements => 5,
+            Self::Git => 6,
+            Self::Script => 7,
+            Self::Filesystem => 8,
+            Self::Store => 9,
+            Self::Partial => 10,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_kept_with_their_hint() {
+        let missing = Path::new("/nonexistent/agpm");
+        let e = std::fs::remove_dir_all(missing)
+            .at("remove", missing)
+            .unwrap_err();
+        assert_eq!(e.path, missing);
+        assert!(e
+            .to_string()
+            .starts_with("Unable to remove \"/nonexistent/agpm\""));
+        assert!(e.hint().contains("/nonexistent/agpm"));
+    }
+
+    #[test]
+    fn git_errors_name_their_project() {
+        let Err(e) = git2::Repository::open("/nonexistent/agpm").of("missing") else {
+            panic!("The repository was opened");
+        };
+        assert_eq!(e.project, "missing");
+        assert!(e.to_string().contains("of missing"));
+        assert!(e.hint().contains("missing"));
+    }
+}
@@ -0,0 +1,97 @@
+//! The state of an installed project, beyond its configuration.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The space that a project takes in the disk, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// The sources, where the project is built
+    pub src: u64,
+    /// The copies of the previous versions, that are used to restore them
+    pub old: u64,
+}
+
+impl DiskUsage {
+    /// The space of all the directories together
+    pub fn total(&self) -> u64 {
+        self.src + self.old
+    }
+}
+
+/// The size of all the files in a directory and its subdirectories, without
+/// following links. Missing directories have no size
+pub(crate) fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    std::fs::read_dir(path)?.try_fold(0, |size, entry| Ok(size + dir_size(&entry?.path())?))
+}
+
+/// The directory of a previous version of a project in `old`. The newest one,
+/// `0`, is `old/{dir}`, and the older ones are `old/{dir}~{n}`
+pub fn generation(old: &Path, dir: &str, n: usize) -> PathBuf {
+    match n {
+        0 => old.join(dir),
+        n => old.join(format!("{dir}~{n}")),
+    }
+}
+
+/// The directories of the previous versions of a project in `old`, newest first
+pub fn generations(old: &Path, dir: &str) -> Vec<PathBuf> {
+    (0..)
+        .map(|n| generation(old, dir, n))
+        .take_while(|path| path.exists())
+        .collect()
+}
+
+/// The directory of the project that an entry of `old` is a previous version of
+pub fn generation_dir(entry: &str) -> &str {
+    match entry.rsplit_once('~') {
+        Some((dir, n)) if n.parse::<usize>().is_ok() => dir,
+        _ => entry,
+    }
+}
+
+/// The state of the repository and the directories of an installed project
+#[derive(Debug, Clone)]
+pub struct ProjectInfo {
+    /// The name of the project
+    pub name: String,
+    /// Where the sources of the project are
+    pub src: PathBuf,
+    /// The url that is stored in the configuration of the project
+    pub url: String,
+    /// The url of the remote of the repository, which is used to update it
+    pub remote_url: Option<String>,
+    /// The reference that is stored in the configuration of the project
+    pub ref_string: String,
+    /// The branch that the repository is at, if it's at one
+    pub branch: Option<String>,
+    /// The commit that the repository is at
+    pub commit: String,
+    /// The commit described from the closest tag, like `v1.2-3-gabcdef1`
+    pub describe: Option<String>,
+    /// Whether the tracked files have changes that aren't committed. Files
+    /// that the build creates are usually untracked, and they don't count
+    pub dirty: bool,
+    /// When the repository was last fetched from its remote, if ever
+    pub last_fetch: Option<SystemTime>,
+    /// The commits of the versions kept in the `old()` directory, which can
+    /// be restored one after the other, newest first
+    pub generations: Vec<String>,
+    /// The space that the project takes in the disk
+    pub disk_usage: DiskUsage,
+}
+
+impl ProjectInfo {
+    /// Whether the remote of the repository is the url of the configuration
+    pub fn remote_matches(&self) -> bool {
+        self.remote_url.as_deref() == Some(self.url.as_str())
+    }
+}
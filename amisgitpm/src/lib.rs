@@ -2,8 +2,22 @@
 #![doc = include_str!("../README.md")]
 
 pub mod project;
-pub use project::{ProjectIface, ProjectStore};
+pub use project::{BuildStatus, Hook, ProjectIface, ProjectStore, UpdatePolicy};
 pub mod dirs;
 pub use dirs::Directories;
+pub mod batch;
+pub use batch::{BatchReport, Outcome};
+pub mod deps;
+pub mod doctor;
+pub mod errors;
+pub use doctor::Finding;
+pub use errors::{CommonPMErrors, ErrorClass, GitContext, GitError, Hint, PathContext, PathError};
+pub mod info;
+pub use info::{DiskUsage, ProjectInfo};
 pub mod pm;
-pub use pm::{CommonPMErrors, PMInteractive, PMOperations, PMProgrammatic};
+pub mod query;
+pub use query::Query;
+pub mod requirements;
+pub mod sync;
+pub use pm::{PMInteractive, PMOperations, PMProgrammatic};
+pub use sync::Change;
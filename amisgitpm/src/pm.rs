@@ -22,39 +22,17 @@
 //! `PMProgramatic` or reimplement some other using `PMOperations`
 //!
 
-use crate::{Directories, ProjectIface, ProjectStore};
+use crate::{
+    batch, deps,
+    doctor::{self, Finding},
+    info::{self, dir_size, DiskUsage, ProjectInfo},
+    requirements, BatchReport, BuildStatus, Change, CommonPMErrors, Directories, GitContext,
+    GitError, Hook, Outcome, PathContext, PathError, ProjectIface, ProjectStore, Query,
+};
 use git2::Repository;
 use std::path::{Path, PathBuf};
-
-/// An error class that's needed to provide methods
-#[non_exhaustive]
-#[derive(Debug, Clone)]
-pub enum CommonPMErrors {
-    /// Attempting to install an already existing project
-    AlreadyExisting,
-    /// Attempting to get a non existing project from the store
-    NonExisting,
-    /// Couldn't parse an OsStr as a utf-8 str
-    Os2Str,
-    /// Can't find a reference to detached head
-    BadRef,
-    /// Couldn't update with a fast forward
-    ImposibleUpdate,
-}
-impl std::error::Error for CommonPMErrors {}
-impl std::fmt::Display for CommonPMErrors {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::AlreadyExisting => {
-                write!(f, "A project with that name or directory already exists")
-            }
-            Self::NonExisting => write!(f, "That project that doesn't exist!"),
-            Self::Os2Str => write!(f, "Couldn't convert from &Osstr to utf-8 &str"),
-            Self::BadRef => write!(f, "Couldn't find a reference to a non detached HEAD"),
-            Self::ImposibleUpdate => write!(f, "Update couldn't be solved by a fast forward."),
-        }
-    }
-}
+use std::time::Instant;
+use tracing::{debug, info, info_span};
 
 /// A trait that concerns itself with the "low level" operations of the project
 /// manager, with how things are done.
@@ -70,7 +48,11 @@ where
     type Project: ProjectIface;
     /// A type that can hold all the errors originated from the different functions.
     /// It's the same for the `PMOperations` and `PMInteractive`
-    type Error: std::error::Error + From<std::io::Error> + From<CommonPMErrors> + From<git2::Error>;
+    type Error: std::error::Error
+        + From<std::io::Error>
+        + From<PathError>
+        + From<CommonPMErrors>
+        + From<GitError>;
     /// Create a project manager struct, type or whatever
     fn new() -> Result<Self, Self::Error>;
     /// Map the errors created by your store to project manager errors
@@ -119,7 +101,9 @@ where
     /// - Failure cloning the repo.
     fn download(&self, prj: &Self::Project) -> Result<(Repository, PathBuf), Self::Error> {
         let git_dir = self.get_dirs().git().join(prj.get_dir());
-        let repo = Repository::clone(prj.get_url(), &git_dir)?;
+        let start = Instant::now();
+        let repo = Repository::clone(prj.get_url(), &git_dir).of(prj.get_name())?;
+        info!(url = prj.get_url(), elapsed = ?start.elapsed(), "Cloned");
         Ok((repo, git_dir))
     }
 
@@ -130,16 +114,193 @@ where
     /// - If there is no reference to set the head to -> `CommonPMErrors::BadRef`
     /// - Setting the head to the head of the reference
     fn switch_branch(&self, prj: &Self::Project, repo: &Repository) -> Result<(), Self::Error> {
-        let (obj, refe) = repo.revparse_ext(prj.get_ref_string())?;
-        repo.checkout_tree(&obj, None)?;
+        let (obj, refe) = repo.revparse_ext(prj.get_ref_string()).of(prj.get_name())?;
+        repo.checkout_tree(&obj, None).of(prj.get_name())?;
         if let Some(gref) = refe {
-            repo.set_head(gref.name().unwrap())?;
+            repo.set_head(gref.name().unwrap()).of(prj.get_name())?;
         } else {
             Err(CommonPMErrors::BadRef)?;
         }
         Ok(())
     }
 
+    /// Move the branch of the project to an exact commit, as when installing
+    /// from a lockfile
+    /// # Errors
+    /// - If the commit isn't in the repository -> `CommonPMErrors::MissingCommit`
+    /// - Checking out the commit
+    fn reset_to_commit(
+        &self,
+        prj: &Self::Project,
+        repo: &Repository,
+        commit: &str,
+    ) -> Result<(), Self::Error> {
+        let obj = repo
+            .revparse_single(commit)
+            .and_then(|obj| obj.peel(git2::ObjectType::Commit))
+            .map_err(|_| {
+                CommonPMErrors::MissingCommit(prj.get_name().to_string(), commit.to_string())
+            })?;
+        repo.reset(&obj, git2::ResetType::Hard, None)
+            .of(prj.get_name())?;
+        Ok(())
+    }
+    /// Get the commit that the sources of a project are at, that is, the last
+    /// commit that was built
+    /// # Errors
+    /// - Opening the repository in the `src()` directory
+    /// - Getting the commit that the head points to
+    fn get_commit(&self, prj: &Self::Project) -> Result<String, Self::Error> {
+        let repo = Repository::open(self.get_dirs().src().join(prj.get_dir())).of(prj.get_name())?;
+        let commit = repo
+            .head()
+            .of(prj.get_name())?
+            .peel_to_commit()
+            .of(prj.get_name())?;
+        Ok(commit.id().to_string())
+    }
+    /// Fetch the reference of a project into its sources, without changing
+    /// them, and get the commit that it points to upstream. Projects without
+    /// remotes get the commit of their sources
+    /// # Errors
+    /// - Opening the repository in the `src()` directory
+    /// - Fetching the reference and getting the commit it points to
+    fn get_remote_commit(&self, prj: &Self::Project) -> Result<String, Self::Error> {
+        let repo = Repository::open(self.get_dirs().src().join(prj.get_dir())).of(prj.get_name())?;
+        let remotes = repo.remotes().of(prj.get_name())?;
+        let Some(remote) = remotes.get(0) else {
+            return self.get_commit(prj);
+        };
+        let start = Instant::now();
+        repo.find_remote(remote)
+            .of(prj.get_name())?
+            .fetch(&[prj.get_ref_string()], None, None)
+            .of(prj.get_name())?;
+        info!(project = prj.get_name(), elapsed = ?start.elapsed(), "Fetched");
+        let commit = repo
+            .find_reference("FETCH_HEAD")
+            .of(prj.get_name())?
+            .peel_to_commit()
+            .of(prj.get_name())?;
+        Ok(commit.id().to_string())
+    }
+
+    /// Rename the directories of a project in the `src()` and `old()` directories,
+    /// from `from` to `to`. If the second rename fails the first is undone
+    /// # Errors
+    /// - If there are directories named `to` already -> `CommonPMErrors::AlreadyExisting`
+    /// - Renaming the directories
+    fn move_dirs(&self, from: &str, to: &str) -> Result<(), Self::Error> {
+        let (src, old) = (self.get_dirs().src(), self.get_dirs().old());
+        if src.join(to).exists() || old.join(to).exists() {
+            Err(CommonPMErrors::AlreadyExisting(to.to_string()))?;
+        }
+        let (src_from, src_to) = (src.join(from), src.join(to));
+        std::fs::rename(&src_from, &src_to).at("move", &src_from)?;
+        for (n, path) in info::generations(&old, from).iter().enumerate() {
+            if let Err(e) = std::fs::rename(path, info::generation(&old, to, n)) {
+                std::fs::rename(&src_to, &src_from).at("move", &src_to)?;
+                Err(e).at("move", path)?;
+            }
+        }
+        Ok(())
+    }
+    /// How many previous versions of each project are kept in the `old()`
+    /// directory. By default one
+    fn get_generations(&self) -> usize {
+        1
+    }
+    /// How many projects are fetched at once when several are updated. By
+    /// default one
+    fn get_fetch_jobs(&self) -> usize {
+        1
+    }
+    /// How many projects are built at once when several are updated. By
+    /// default one
+    fn get_build_jobs(&self) -> usize {
+        1
+    }
+    /// Keep the sources of a project as its newest previous version, in
+    /// `old()/{dir}`. The older versions are moved to `old()/{dir}~1`,
+    /// `old()/{dir}~2`... and those beyond [`Self::get_generations`] are removed
+    /// # Errors
+    /// - Removing, renaming or copying the directories
+    fn push_generation(&self, prj: &Self::Project) -> Result<(), Self::Error> {
+        let (old, dir) = (self.get_dirs().old(), prj.get_dir());
+        let kept = self.get_generations();
+        for path in info::generations(&old, dir)
+            .iter()
+            .skip(kept.saturating_sub(1))
+        {
+            std::fs::remove_dir_all(path).at("remove", path)?;
+        }
+        if kept == 0 {
+            return Ok(());
+        }
+        for n in (0..kept - 1).rev() {
+            let from = info::generation(&old, dir, n);
+            if from.exists() {
+                std::fs::rename(&from, info::generation(&old, dir, n + 1)).at("move", &from)?;
+            }
+        }
+        self.copy_directory(
+            self.get_dirs().src().join(dir),
+            info::generation(&old, dir, 0),
+        )?;
+        debug!(dir, kept, "Kept the sources as the previous version");
+        Ok(())
+    }
+    /// Remove all the previous versions of a project
+    /// # Errors
+    /// - Removing the directories
+    fn drop_generations(&self, dir: &str) -> Result<(), Self::Error> {
+        for path in info::generations(&self.get_dirs().old(), dir) {
+            std::fs::remove_dir_all(&path).at("remove", &path)?;
+        }
+        Ok(())
+    }
+    /// Make the repository in the `src()` directory of `prj` follow its url and
+    /// reference, when they are different from those of `old`. The remote url is
+    /// changed, the reference is fetched, and the sources are checked out at it.
+    /// If that fails the remote url is set back
+    /// # Errors
+    /// - Opening the repository or changing the remote url
+    /// - Fetching the reference or checking it out
+    fn follow_remote(&self, old: &Self::Project, prj: &Self::Project) -> Result<(), Self::Error> {
+        if old.get_url() == prj.get_url() && old.get_ref_string() == prj.get_ref_string() {
+            return Ok(());
+        }
+        let repo = Repository::open(self.get_dirs().src().join(prj.get_dir())).of(prj.get_name())?;
+        let remote = repo
+            .remotes()
+            .of(prj.get_name())?
+            .get(0)
+            .unwrap_or("origin")
+            .to_string();
+        repo.remote_set_url(&remote, prj.get_url())
+            .of(prj.get_name())?;
+        let checkout = || -> Result<(), git2::Error> {
+            let start = Instant::now();
+            repo.find_remote(&remote)?
+                .fetch(&[prj.get_ref_string()], None, None)?;
+            info!(url = prj.get_url(), elapsed = ?start.elapsed(), "Fetched");
+            let commit = repo.find_reference("FETCH_HEAD")?.peel_to_commit()?;
+            repo.reference(
+                prj.get_ref_string(),
+                commit.id(),
+                true,
+                "Follow the edited reference",
+            )?;
+            repo.set_head(prj.get_ref_string())?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
+        };
+        if let Err(e) = checkout() {
+            repo.remote_set_url(&remote, old.get_url())
+                .of(prj.get_name())?;
+            Err(e).of(prj.get_name())?;
+        }
+        Ok(())
+    }
     /// Move from wherever to the projects subdirectory in the sources directory
     /// # Errors
     /// - Deleting the directories (in established in `Dirs::new().unwrap().src` or `path`)
@@ -147,23 +308,54 @@ where
     fn mv(&self, prj: &Self::Project, path: &Path) -> Result<(), Self::Error> {
         let src_dir = self.get_dirs().src().join(prj.get_dir());
         if src_dir.exists() {
-            std::fs::remove_dir_all(&src_dir)?;
+            std::fs::remove_dir_all(&src_dir).at("remove", &src_dir)?;
         }
         self.copy_directory(path, &src_dir)?;
-        std::fs::remove_dir_all(path)?;
+        std::fs::remove_dir_all(path).at("remove", path)?;
         Ok(())
     }
-    /// Run the build script from the `src()` directory.
+    /// Run the build script from the `src()` directory, recording how it went
+    /// for [`Self::build_status`].
     /// # Errors
     /// - Script runner failure
+    /// - Writing the build status
     fn build(&self, prj: &Self::Project) -> Result<(), Self::Error> {
-        self.script_runner(prj.get_dir(), prj.get_install())
+        let start = Instant::now();
+        let built = self.script_runner(prj.get_name(), prj.get_dir(), prj.get_install());
+        let status = self.build_status_file(prj);
+        let content = if built.is_ok() { "built" } else { "failed" };
+        std::fs::write(&status, content).at("write", &status)?;
+        built?;
+        info!(project = prj.get_name(), elapsed = ?start.elapsed(), "Built");
+        Ok(())
+    }
+    /// How the build script of the project went the last time it was run on
+    /// its current sources
+    fn build_status(&self, prj: &Self::Project) -> BuildStatus {
+        match std::fs::read_to_string(self.build_status_file(prj)).as_deref() {
+            Ok("built") => BuildStatus::Built,
+            Ok("failed") => BuildStatus::Failed,
+            _ => BuildStatus::NotBuilt,
+        }
+    }
+    /// The file that keeps the build status of the project. It's in the git
+    /// directory of the sources, so that it doesn't change their working tree
+    /// and it goes away with them
+    fn build_status_file(&self, prj: &Self::Project) -> PathBuf {
+        self.get_dirs()
+            .src()
+            .join(prj.get_dir())
+            .join(".git")
+            .join("agpm-build-status")
     }
     /// Run the uninstall script from the `src()` directory
     /// # Errors
     /// - Script runner failure
     fn unbuild(&self, prj: &Self::Project) -> Result<(), Self::Error> {
-        self.script_runner(prj.get_dir(), prj.get_uninstall())
+        let start = Instant::now();
+        self.script_runner(prj.get_name(), prj.get_dir(), prj.get_uninstall())?;
+        info!(project = prj.get_name(), elapsed = ?start.elapsed(), "Unbuilt");
+        Ok(())
     }
     /// Update a repo, getting the latest changes if they can be fast forwarded to,
     /// and ensuring that the correct reference is updated. If any updates have
@@ -178,29 +370,80 @@ where
     /// - Resolving the merge with Fast-Forward strategy
     /// - Seting the head to the new head
     fn update_repo(&self, prj: &Self::Project, repo: &Repository) -> Result<bool, Self::Error> {
-        let remotes = repo.remotes()?;
+        let remotes = repo.remotes().of(prj.get_name())?;
         if !remotes.is_empty() {
-            repo.find_remote(remotes.get(0).unwrap_or("origin"))?
-                .fetch(&[prj.get_ref_string()], None, None)?;
+            let start = Instant::now();
+            repo.find_remote(remotes.get(0).unwrap_or("origin"))
+                .of(prj.get_name())?
+                .fetch(&[prj.get_ref_string()], None, None)
+                .of(prj.get_name())?;
+            info!(project = prj.get_name(), elapsed = ?start.elapsed(), "Fetched");
         }
-        let fetch_head = repo.find_reference("FETCH_HEAD")?;
-        let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
-        let analysis = repo.merge_analysis(&[&fetch_commit])?;
+        let fetch_head = repo.find_reference("FETCH_HEAD").of(prj.get_name())?;
+        let fetch_commit = repo
+            .reference_to_annotated_commit(&fetch_head)
+            .of(prj.get_name())?;
+        let analysis = repo.merge_analysis(&[&fetch_commit]).of(prj.get_name())?;
         if analysis.0.is_up_to_date() {
+            debug!(project = prj.get_name(), "Already up to date");
             return Ok(false); // early return
         } else if analysis.0.is_fast_forward() {
-            let mut reference = repo.find_reference(prj.get_ref_string())?;
-            reference.set_target(fetch_commit.id(), "Fast-Forward")?;
-            repo.set_head(prj.get_ref_string())?;
-            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+            debug!(project = prj.get_name(), commit = %fetch_commit.id(), "Fast forwarding");
+            let mut reference = repo
+                .find_reference(prj.get_ref_string())
+                .of(prj.get_name())?;
+            reference
+                .set_target(fetch_commit.id(), "Fast-Forward")
+                .of(prj.get_name())?;
+            repo.set_head(prj.get_ref_string()).of(prj.get_name())?;
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
+                .of(prj.get_name())?;
         } else {
-            Err(CommonPMErrors::ImposibleUpdate)?;
+            Err(CommonPMErrors::ImposibleUpdate(prj.get_name().to_string()))?;
         }
         Ok(true)
     }
-    /// Run a script to install or uninstall a project
+    /// Check that every requirement of the project is met in this host, so that
+    /// a build doesn't fail midway.
+    /// # Errors
+    /// - If any isn't, listing all of those -> `CommonPMErrors::MissingRequirements`
+    fn check_requirements(&self, prj: &Self::Project) -> Result<(), Self::Error> {
+        let unmet = requirements::unmet(prj.get_requirements());
+        if !unmet.is_empty() {
+            Err(CommonPMErrors::MissingRequirements(
+                prj.get_name().to_string(),
+                unmet,
+            ))?;
+        }
+        Ok(())
+    }
+    /// Get the script to run for every project at some point of their life.
+    /// By default there are no global hooks
+    fn get_global_hook(&self, _hook: Hook) -> &[String] {
+        &[]
+    }
+    /// Run the global script and then the project's script for a hook. Hooks are
+    /// run from the `src()` directory of the project, except `Hook::PostUninstall`
+    /// which is run from the `src()` directory itself.
+    /// # Errors
+    /// - Script runner failure
+    fn run_hook(&self, prj: &Self::Project, hook: Hook) -> Result<(), Self::Error> {
+        let dir = match hook {
+            Hook::PostUninstall => "",
+            _ => prj.get_dir(),
+        };
+        for script in [self.get_global_hook(hook), prj.get_hook(hook)] {
+            if !script.is_empty() {
+                self.script_runner(prj.get_name(), dir, script)?;
+            }
+        }
+        Ok(())
+    }
+    /// Run a script of the project `prj_name` to install or uninstall it, from
+    /// the directory `dir` of `src()`
     fn script_runner<T: AsRef<str>, Q: AsRef<[T]>>(
         &self,
+        prj_name: &str,
         dir: &str,
         script: Q,
     ) -> Result<(), Self::Error>;
@@ -212,100 +455,991 @@ pub trait PMProgrammatic: PMOperations {
     /// Install a project from a known Project in which all parameters are known
     /// # Errors
     /// - If there is a project with that name or directory already in use -> `CommonPMErrors::AlreadyExisting`
+    /// - If any of its dependencies isn't installed -> `CommonPMErrors::MissingDependencies`
+    /// - If the host doesn't meet its requirements -> `CommonPMErrors::MissingRequirements`
     /// - Switching branches
     /// - Moving dirs
     /// - Adding to the store
-    /// - Building the project
+    /// - Building the project or running its install hooks
     fn install(&mut self, prj: Self::Project) -> Result<(), Self::Error> {
-        if !self.get_store().check_unique(prj.get_name(), prj.get_dir()) {
-            Err(CommonPMErrors::AlreadyExisting)?;
+        self.install_at(prj, None)
+    }
+    /// Install a project like [`Self::install`], and if a commit is given, with
+    /// its branch at that exact commit instead of at the latest one
+    /// # Errors
+    /// - The errors of [`Self::install`]
+    /// - If the commit isn't in the repository -> `CommonPMErrors::MissingCommit`
+    fn install_at(&mut self, prj: Self::Project, commit: Option<&str>) -> Result<(), Self::Error> {
+        let _span = info_span!("install", project = prj.get_name()).entered();
+        if !self.get_store().check_name_free(prj.get_name()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_name().to_string()))?;
         }
+        if !self.get_store().check_dir_free(prj.get_dir()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_dir().to_string()))?;
+        }
+        self.check_dependencies(&prj)?;
+        self.check_requirements(&prj)?;
         let (repo, git_dir) = self.download(&prj)?;
-        self.switch_branch(&prj, &repo)?;
+        let checkout = self.switch_branch(&prj, &repo).and_then(|_| match commit {
+            Some(commit) => self.reset_to_commit(&prj, &repo, commit),
+            None => Ok(()),
+        });
+        if let Err(e) = checkout {
+            std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+            return Err(e);
+        }
         self.mv(&prj, &git_dir)?;
         self.get_mut_store()
             .add(prj.clone())
             .map_err(Self::map_store_error)?;
+        self.run_hook(&prj, Hook::PreInstall)?;
+        self.build(&prj)?;
+        self.run_hook(&prj, Hook::PostInstall)?;
+        Ok(())
+    }
+    /// Install a project like [`Self::install`], pulling in first the
+    /// dependencies that aren't installed, which are taken from `available`,
+    /// like the projects of an exported file
+    /// # Errors
+    /// - The errors of [`Self::with_dependencies`]
+    /// - Any error from installing each of the projects
+    fn install_with_dependencies(
+        &mut self,
+        prj: Self::Project,
+        available: &[Self::Project],
+    ) -> Result<(), Self::Error> {
+        for prj in self.with_dependencies(vec![prj], available)? {
+            self.install(prj)?;
+        }
+        Ok(())
+    }
+    /// Install several projects, pulling in the dependencies among them in
+    /// topological order, so that every project is built after the projects it
+    /// depends on. Dependencies that are not among `prjs` must be already
+    /// installed, which is checked before installing any of them.
+    /// # Errors
+    /// - The errors of [`Self::with_dependencies`]
+    /// - Any error from installing each of the projects
+    fn install_many(&mut self, prjs: Vec<Self::Project>) -> Result<(), Self::Error> {
+        for prj in self.with_dependencies(prjs, &[])? {
+            self.install(prj)?;
+        }
+        Ok(())
+    }
+    /// Install several projects non interactively, like [`Self::install_many`],
+    /// but going on after a project fails. Projects whose name or directory is
+    /// already in use are skipped.
+    /// # Errors
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    ///
+    /// The errors from installing each of the projects are in the report
+    fn install_batch(
+        &mut self,
+        prjs: Vec<Self::Project>,
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        self.install_batch_at(prjs.into_iter().map(|prj| (prj, None)).collect())
+    }
+    /// Install several projects like [`Self::install_batch`], each of them at
+    /// the commit it's paired with, if any. See [`Self::install_at`]
+    /// # Errors
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    ///
+    /// The errors from installing each of the projects are in the report
+    fn install_batch_at(
+        &mut self,
+        prjs: Vec<(Self::Project, Option<String>)>,
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        let refs: Vec<&Self::Project> = prjs.iter().map(|(prj, _)| prj).collect();
+        let order: Vec<Self::Project> = deps::topological_order(&refs)?
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut commits: std::collections::HashMap<String, String> = prjs
+            .into_iter()
+            .filter_map(|(prj, commit)| Some((prj.get_name().to_string(), commit?)))
+            .collect();
+        let mut report = BatchReport::default();
+        for prj in order {
+            let name = prj.get_name().to_string();
+            let outcome = if !self.get_store().check_name_free(&name) {
+                Outcome::Skipped("already installed".into())
+            } else if !self.get_store().check_dir_free(prj.get_dir()) {
+                Outcome::Skipped(format!("the directory {} is in use", prj.get_dir()))
+            } else {
+                match self.install_at(prj, commits.remove(&name).as_deref()) {
+                    Ok(()) => Outcome::Done,
+                    Err(e) => Outcome::Failed(e),
+                }
+            };
+            report.push(name, outcome);
+        }
+        Ok(report)
+    }
+    /// Replace an installed project with a project whose sources come from a
+    /// different url, reference or directory, getting the sources again, at a
+    /// commit if given. The old sources are unbuilt and the new ones built, as
+    /// in an update, and the old ones are kept as the previous version, so
+    /// [`Self::restore`] takes the project back to them. Projects that depend on
+    /// it are kept.
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If the new name or directory is used by other project -> `CommonPMErrors::AlreadyExisting`
+    /// - If it's renamed and other projects depend on it -> `CommonPMErrors::HasDependents`
+    /// - If any of its dependencies isn't installed -> `CommonPMErrors::MissingDependencies`
+    /// - If the host doesn't meet its requirements -> `CommonPMErrors::MissingRequirements`
+    /// - If the commit isn't in the repository -> `CommonPMErrors::MissingCommit`
+    /// - Cloning, switching branches or moving dirs
+    /// - Substituting the project in the store
+    /// - Building the projects or running the update hooks
+    fn repoint<T: AsRef<str>>(
+        &mut self,
+        prj_name: T,
+        prj: Self::Project,
+        commit: Option<&str>,
+    ) -> Result<(), Self::Error> {
+        let _span = info_span!("repoint", project = prj_name.as_ref()).entered();
+        let old = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?
+            .clone();
+        if old.get_name() != prj.get_name() && !self.get_store().check_name_free(prj.get_name()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_name().to_string()))?;
+        }
+        if old.get_name() != prj.get_name() {
+            // Their dependencies would still have the old name
+            let dependents = self.get_dependents(old.get_name())?;
+            if !dependents.is_empty() {
+                Err(CommonPMErrors::HasDependents(
+                    old.get_name().to_string(),
+                    dependents
+                        .iter()
+                        .map(|e| e.get_name().to_string())
+                        .collect(),
+                ))?;
+            }
+        }
+        if old.get_dir() != prj.get_dir() && !self.get_store().check_dir_free(prj.get_dir()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_dir().to_string()))?;
+        }
+        self.check_dependencies(&prj)?;
+        self.check_requirements(&prj)?;
+        let (repo, git_dir) = self.download(&prj)?;
+        let checkout = self.switch_branch(&prj, &repo).and_then(|_| match commit {
+            Some(commit) => self.reset_to_commit(&prj, &repo, commit),
+            None => Ok(()),
+        });
+        let prepared = checkout
+            .and_then(|_| self.push_generation(&old))
+            .and_then(|_| self.run_hook(&old, Hook::PreUpdate))
+            .and_then(|_| self.unbuild(&old));
+        if let Err(e) = prepared {
+            std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+            return Err(e);
+        }
+        if old.get_dir() != prj.get_dir() {
+            // The previous versions follow the project to its new directory
+            let old_dir = self.get_dirs().old();
+            self.drop_generations(prj.get_dir())?;
+            for (n, path) in info::generations(&old_dir, old.get_dir())
+                .iter()
+                .enumerate()
+            {
+                std::fs::rename(path, info::generation(&old_dir, prj.get_dir(), n))
+                    .at("move", path)?;
+            }
+            let old_src = self.get_dirs().src().join(old.get_dir());
+            if old_src.exists() {
+                std::fs::remove_dir_all(&old_src).at("remove", &old_src)?;
+            }
+        }
+        self.mv(&prj, &git_dir)?;
+        self.get_mut_store()
+            .edit(prj_name.as_ref(), prj.clone())
+            .map_err(Self::map_store_error)?;
         self.build(&prj)?;
+        self.run_hook(&prj, Hook::PostUpdate)?;
         Ok(())
     }
+    /// Compute the changes that take the installed projects to the `desired`
+    /// ones, which are matched by name. Projects paired with a commit are
+    /// also got again if their sources are at another commit. Installed
+    /// projects that aren't desired are uninstalled.
+    ///
+    /// The changes are in the order in which they can be applied with
+    /// [`Self::apply_sync`]: first the uninstalls, from the last dependent to
+    /// the first, and then the rest, with dependencies first.
+    /// # Errors
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    fn plan_sync(
+        &self,
+        desired: Vec<(Self::Project, Option<String>)>,
+    ) -> Result<Vec<Change<Self::Project>>, Self::Error>
+    where
+        Self::Project: PartialEq,
+    {
+        let mut changes = vec![];
+        for prj in deps::topological_order(&self.get_all())?.iter().rev() {
+            if !desired.iter().any(|(e, _)| e.get_name() == prj.get_name()) {
+                changes.push(Change::Uninstall((*prj).clone()));
+            }
+        }
+        let refs: Vec<&Self::Project> = desired.iter().map(|(prj, _)| prj).collect();
+        for prj in deps::topological_order(&refs)? {
+            let commit = desired
+                .iter()
+                .find(|(e, _)| e.get_name() == prj.get_name())
+                .and_then(|(_, commit)| commit.clone());
+            let change = match self.get_one(prj.get_name()) {
+                None => Change::Install(prj.clone(), commit),
+                Some(old)
+                    if old.get_url() != prj.get_url()
+                        || old.get_ref_string() != prj.get_ref_string()
+                        || old.get_dir() != prj.get_dir()
+                        || (commit.is_some() && self.get_commit(old).ok() != commit) =>
+                {
+                    Change::Repoint {
+                        from: old.clone(),
+                        to: prj.clone(),
+                        commit,
+                    }
+                }
+                Some(old) if old != prj => Change::Edit {
+                    from: old.clone(),
+                    to: prj.clone(),
+                },
+                Some(_) => continue,
+            };
+            changes.push(change);
+        }
+        Ok(changes)
+    }
+    /// Apply the changes computed by [`Self::plan_sync`], going on after a
+    /// change fails
+    fn apply_sync(&mut self, changes: Vec<Change<Self::Project>>) -> BatchReport<Self::Error> {
+        let mut report = BatchReport::default();
+        for change in changes {
+            let name = change.name().to_string();
+            let result = match change {
+                Change::Install(prj, commit) => self.install_at(prj, commit.as_deref()),
+                Change::Uninstall(prj) => self.uninstall(prj.get_name()),
+                Change::Repoint { from, to, commit } => {
+                    self.repoint(from.get_name(), to, commit.as_deref())
+                }
+                Change::Edit { from, to } => self
+                    .edit(from.get_name(), to.clone())
+                    .and_then(|_| self.build(&to)),
+            };
+            match result {
+                Ok(()) => report.push(name, Outcome::Done),
+                Err(e) => report.push(name, Outcome::Failed(e)),
+            }
+        }
+        report
+    }
     /// Uninstall a project given it's name
     /// # Errors
     /// - Unable to get the project -> `CommonPMErrors::NonExisting`
-    /// - Unable to run the uninstall script
+    /// - If other projects depend on it -> `CommonPMErrors::HasDependents`
+    /// - Unable to run the uninstall script or the uninstall hooks
     /// - Unable to delete directories -> Normally permissions errors.
     /// - Unable to remove from store.
     fn uninstall<T: AsRef<str>>(&mut self, prj_name: T) -> Result<(), Self::Error> {
+        let _span = info_span!("uninstall", project = prj_name.as_ref()).entered();
         let prj = self
             .get_one(prj_name.as_ref())
-            .ok_or(CommonPMErrors::NonExisting)?;
-        let dir = &prj.get_dir();
-        self.unbuild(prj)?;
-        let src_dir = self.get_dirs().src().join(dir);
-        std::fs::remove_dir_all(src_dir)?;
-        let old_dir = self.get_dirs().old().join(dir);
-        if old_dir.exists() {
-            std::fs::remove_dir_all(old_dir)?;
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let dependents = self.get_dependents(prj_name.as_ref())?;
+        if !dependents.is_empty() {
+            Err(CommonPMErrors::HasDependents(
+                prj_name.as_ref().to_string(),
+                dependents
+                    .iter()
+                    .map(|e| e.get_name().to_string())
+                    .collect(),
+            ))?;
         }
+        let prj = prj.clone();
+        let dir = prj.get_dir();
+        self.run_hook(&prj, Hook::PreUninstall)?;
+        self.unbuild(&prj)?;
+        let src_dir = self.get_dirs().src().join(dir);
+        std::fs::remove_dir_all(&src_dir).at("remove", &src_dir)?;
+        self.drop_generations(dir)?;
         self.get_mut_store()
             .remove(prj_name.as_ref())
             .map_err(Self::map_store_error)?;
+        self.run_hook(&prj, Hook::PostUninstall)?;
         Ok(())
     }
-    /// Update a project given it's name
+    /// Uninstall a project given it's name, after uninstalling every project that
+    /// depends on it, from the last dependent to the first.
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Any error from uninstalling each of the projects
+    fn uninstall_cascade<T: AsRef<str>>(&mut self, prj_name: T) -> Result<(), Self::Error> {
+        self.get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let dependents: Vec<String> = self
+            .get_dependents(prj_name.as_ref())?
+            .iter()
+            .rev()
+            .map(|e| e.get_name().to_string())
+            .collect();
+        for dependent in dependents {
+            self.uninstall(dependent)?;
+        }
+        self.uninstall(prj_name)
+    }
+    /// Uninstall several projects, going on after a project fails. The
+    /// projects that depend on others go first, and a project is skipped while
+    /// a project of the batch that depends on it is still installed. The
+    /// report is in the order in which they were uninstalled
+    /// # Errors
+    /// - If any of the projects isn't installed -> `CommonPMErrors::NonExisting`
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    ///
+    /// The errors from uninstalling each of the projects are in the report
+    fn uninstall_batch<T: AsRef<str>>(
+        &mut self,
+        prj_names: &[T],
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        let prjs = prj_names
+            .iter()
+            .map(|name| {
+                self.get_one(name.as_ref())
+                    .ok_or_else(|| CommonPMErrors::NonExisting(name.as_ref().to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let names: Vec<String> = deps::topological_order(&prjs)?
+            .iter()
+            .rev()
+            .map(|e| e.get_name().to_string())
+            .collect();
+        let mut report = BatchReport::default();
+        for name in &names {
+            let blocking = self
+                .get_dependents(name)?
+                .into_iter()
+                .map(|e| e.get_name())
+                .find(|dependent| names.iter().any(|e| e == dependent))
+                .map(str::to_string);
+            let outcome = match blocking {
+                Some(dependent) => {
+                    Outcome::Skipped(format!("{dependent} depends on it and is still installed"))
+                }
+                None => match self.uninstall(name) {
+                    Ok(()) => Outcome::Done,
+                    Err(e) => Outcome::Failed(e),
+                },
+            };
+            report.push(name, outcome);
+        }
+        Ok(report)
+    }
+    /// Update a project given it's name. Returns whether there were new
+    /// changes, that is, if it was built again
     /// # Errors
     /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If the host doesn't meet its requirements -> `CommonPMErrors::MissingRequirements`
     /// - Unable to copy directories
     /// - Unable to open the repo
     /// - Unable to switch to the established branch
     /// - Unable to update the repo
     /// - Unable to move the project
-    /// - Unable to build the project
-    fn update<T: AsRef<str>>(&self, prj_name: T) -> Result<(), Self::Error> {
+    /// - Unable to build the project or running its update hooks
+    /// - Unable to rebuild the projects that depend on it
+    fn update<T: AsRef<str>>(&self, prj_name: T) -> Result<bool, Self::Error> {
+        if !self.fetch_update(prj_name.as_ref())? {
+            return Ok(false);
+        }
+        self.apply_update(prj_name.as_ref())?;
+        self.rebuild_dependents(prj_name)?;
+        Ok(true)
+    }
+    /// The first half of [`Self::update`], that only needs the network. The
+    /// sources of a project are copied to the `git()` directory and the new
+    /// changes are fetched there, without building them. Returns whether there
+    /// were new changes. If there weren't, or fetching them fails, the copy is
+    /// removed
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If the host doesn't meet its requirements -> `CommonPMErrors::MissingRequirements`
+    /// - Unable to copy directories
+    /// - Unable to open the repo, switch to the established branch or update it
+    fn fetch_update<T: AsRef<str>>(&self, prj_name: T) -> Result<bool, Self::Error> {
+        let _span = info_span!("update", project = prj_name.as_ref()).entered();
         let prj = self
             .get_one(prj_name.as_ref())
-            .ok_or(CommonPMErrors::NonExisting)?;
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        self.check_requirements(prj)?;
         let dir = prj.get_dir();
         let git_dir = self.get_dirs().git().join(dir);
-        let old_dir = self.get_dirs().old().join(dir);
         let src_dir = self.get_dirs().src().join(dir);
-        self.copy_directory(&src_dir, &old_dir)?;
-        self.copy_directory(&src_dir, &git_dir)?;
-        let repo = Repository::open(&git_dir)?;
-        self.switch_branch(prj, &repo)?;
-        if self.update_repo(prj, &repo)?{
-            self.mv(prj, &git_dir)?;
-            self.build(prj)?;
-        } else {
-            std::fs::remove_dir_all(git_dir)?;
+        let fetched = self.copy_directory(&src_dir, &git_dir).and_then(|_| {
+            let repo = Repository::open(&git_dir).of(prj.get_name())?;
+            self.switch_branch(prj, &repo)?;
+            self.update_repo(prj, &repo)
+        });
+        match fetched {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+                Ok(false)
+            }
+            Err(e) => {
+                if git_dir.exists() {
+                    std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+                }
+                Err(e)
+            }
         }
+    }
+    /// The second half of [`Self::update`], for a project whose new changes
+    /// were fetched with [`Self::fetch_update`]. The sources are kept as the
+    /// previous version, replaced with the fetched ones and built, between the
+    /// update hooks. The projects that depend on it aren't built again. If it
+    /// fails before the sources are replaced, the fetched ones are removed
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Unable to move the project
+    /// - Unable to build the project or running its update hooks
+    fn apply_update<T: AsRef<str>>(&self, prj_name: T) -> Result<(), Self::Error> {
+        let _span = info_span!("update", project = prj_name.as_ref()).entered();
+        let prj = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let git_dir = self.get_dirs().git().join(prj.get_dir());
+        let prepared = self
+            .push_generation(prj)
+            .and_then(|_| self.run_hook(prj, Hook::PreUpdate));
+        if let Err(e) = prepared {
+            std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+            return Err(e);
+        }
+        self.mv(prj, &git_dir)?;
+        self.build(prj)?;
+        self.run_hook(prj, Hook::PostUpdate)?;
         Ok(())
     }
-    /// Install the older version of a project given it's name
+    /// Update several projects, going on after a project fails. Projects
+    /// without new changes are skipped.
+    ///
+    /// First all the projects are fetched, [`PMOperations::get_fetch_jobs`] at
+    /// a time. Then those with new changes are built,
+    /// [`PMOperations::get_build_jobs`] at a time, and always after the
+    /// projects they depend on. Last, the projects that depend on the updated
+    /// ones, and that weren't updated themselves, are built again, also after
+    /// the projects they depend on. Those that were skipped as up to date get
+    /// the outcome of building them again, and the rest are reported after the
+    /// given projects. All of them are in [`BatchReport::rebuilt`]. A project
+    /// isn't built again when one it depends on failed to be.
+    /// The report is in the order of their dependencies
+    /// # Errors
+    /// - If any of the projects isn't installed -> `CommonPMErrors::NonExisting`
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    ///
+    /// The errors from updating each of the projects are in the report
+    fn update_batch<T: AsRef<str>>(
+        &self,
+        prj_names: &[T],
+    ) -> Result<BatchReport<Self::Error>, Self::Error>
+    where
+        Self: Sync,
+        Self::Error: Send,
+    {
+        let prjs = prj_names
+            .iter()
+            .map(|name| {
+                self.get_one(name.as_ref())
+                    .ok_or_else(|| CommonPMErrors::NonExisting(name.as_ref().to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let levels: Vec<Vec<String>> = deps::levels(&prjs)?
+            .iter()
+            .map(|level| level.iter().map(|e| e.get_name().to_string()).collect())
+            .collect();
+        let names: Vec<String> = levels.iter().flatten().cloned().collect();
+        // The projects that may have to be built again are found before
+        // changing anything, so that a cycle doesn't stop the batch half done
+        let all = self.get_all();
+        let mut dependents: Vec<Vec<String>> = vec![];
+        let mut affected: Vec<&Self::Project> = vec![];
+        for name in &names {
+            let found = deps::dependents(&all, name)?;
+            for prj in &found {
+                if !affected.iter().any(|e| e.get_name() == prj.get_name()) {
+                    affected.push(prj);
+                }
+            }
+            dependents.push(found.iter().map(|e| e.get_name().to_string()).collect());
+        }
+        let rebuild_levels: Vec<Vec<String>> = deps::levels(&affected)?
+            .iter()
+            .map(|level| level.iter().map(|e| e.get_name().to_string()).collect())
+            .collect();
+        let fetched = batch::parallel_map(&names, self.get_fetch_jobs(), |name| {
+            self.fetch_update(name)
+        });
+        let mut outcomes: Vec<Option<Outcome<Self::Error>>> = fetched
+            .into_iter()
+            .map(|fetched| match fetched {
+                Ok(true) => None,
+                Ok(false) => Some(Outcome::Skipped("already up to date".into())),
+                Err(e) => Some(Outcome::Failed(e)),
+            })
+            .collect();
+        let mut start = 0;
+        for level in &levels {
+            let range = start..start + level.len();
+            start = range.end;
+            let to_build: Vec<usize> = range.filter(|&idx| outcomes[idx].is_none()).collect();
+            let built = batch::parallel_map(&to_build, self.get_build_jobs(), |&idx| {
+                self.apply_update(&names[idx])
+            });
+            for (idx, built) in to_build.into_iter().zip(built) {
+                outcomes[idx] = Some(match built {
+                    Ok(()) => Outcome::Done,
+                    Err(e) => Outcome::Failed(e),
+                });
+            }
+        }
+        let mut outcomes: Vec<Outcome<Self::Error>> = outcomes.into_iter().flatten().collect();
+        // The dependents of the updated projects, except those that were
+        // updated or failed themselves
+        let to_rebuild: Vec<&str> = dependents
+            .iter()
+            .zip(&outcomes)
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Done))
+            .flat_map(|(dependents, _)| dependents)
+            .map(String::as_str)
+            .filter(
+                |dependent| match names.iter().position(|e| e == dependent) {
+                    Some(idx) => matches!(outcomes[idx], Outcome::Skipped(_)),
+                    None => true,
+                },
+            )
+            .collect();
+        let mut broken: Vec<&str> = vec![];
+        let mut rebuilt: Vec<(&str, Outcome<Self::Error>)> = vec![];
+        for level in &rebuild_levels {
+            let mut ready = vec![];
+            for name in level.iter().filter(|e| to_rebuild.contains(&e.as_str())) {
+                let failed_deps: Vec<&str> = self
+                    .get_one(name)
+                    .map_or(&[][..], |prj| prj.get_dependencies())
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|dep| broken.contains(dep))
+                    .collect();
+                if failed_deps.is_empty() {
+                    ready.push(name.as_str());
+                } else {
+                    let reason = format!("{} failed to be built again", failed_deps.join(", "));
+                    rebuilt.push((name, Outcome::Skipped(reason)));
+                    broken.push(name);
+                }
+            }
+            let built = batch::parallel_map(&ready, self.get_build_jobs(), |name| {
+                let prj = self
+                    .get_one(name)
+                    .ok_or_else(|| CommonPMErrors::NonExisting(name.to_string()))?;
+                self.build(prj)
+            });
+            for (name, built) in ready.into_iter().zip(built) {
+                match built {
+                    Ok(()) => rebuilt.push((name, Outcome::Done)),
+                    Err(e) => {
+                        rebuilt.push((name, Outcome::Failed(e)));
+                        broken.push(name);
+                    }
+                }
+            }
+        }
+        let mut report = BatchReport::default();
+        let mut extra = vec![];
+        for (name, outcome) in rebuilt {
+            report.rebuilt.push(name.to_string());
+            match names.iter().position(|e| e == name) {
+                Some(idx) => outcomes[idx] = outcome,
+                None => extra.push((name, outcome)),
+            }
+        }
+        for (name, outcome) in names.iter().zip(outcomes) {
+            report.push(name, outcome);
+        }
+        for (name, outcome) in extra {
+            report.push(name, outcome);
+        }
+        Ok(report)
+    }
+    /// Check whether there are new commits upstream for a project, without
+    /// changing it. Holds the commit of its sources and the newest one, when
+    /// the sources don't have the newest yet
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Opening the repository or fetching the reference
+    fn outdated<T: AsRef<str>>(
+        &self,
+        prj_name: T,
+    ) -> Result<Option<(String, String)>, Self::Error> {
+        let prj = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let installed = self.get_commit(prj)?;
+        let latest = self.get_remote_commit(prj)?;
+        if installed == latest {
+            return Ok(None);
+        }
+        let repo = Repository::open(self.get_dirs().src().join(prj.get_dir())).of(prj.get_name())?;
+        let ahead = repo
+            .graph_descendant_of(
+                git2::Oid::from_str(&installed).of(prj.get_name())?,
+                git2::Oid::from_str(&latest).of(prj.get_name())?,
+            )
+            .of(prj.get_name())?;
+        Ok((!ahead).then_some((installed, latest)))
+    }
+    /// Get the space that a project takes in the disk, that of its sources and
+    /// that of the copies of its previous versions
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Reading the directories
+    fn disk_usage<T: AsRef<str>>(&self, prj_name: T) -> Result<DiskUsage, Self::Error> {
+        let prj = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        Ok(DiskUsage {
+            src: dir_size(&self.get_dirs().src().join(prj.get_dir()))?,
+            old: info::generations(&self.get_dirs().old(), prj.get_dir())
+                .iter()
+                .try_fold(0, |size, path| {
+                    Ok::<_, std::io::Error>(size + dir_size(path)?)
+                })?,
+        })
+    }
+    /// Get the state of the repository and the directories of a project
     /// # Errors
     /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Opening the repository in the `src()` directory or reading its head
+    /// - Reading the status of its files or its directories
+    fn info<T: AsRef<str>>(&self, prj_name: T) -> Result<ProjectInfo, Self::Error> {
+        let prj = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
+        let src = self.get_dirs().src().join(prj.get_dir());
+        let repo = Repository::open(&src).of(prj.get_name())?;
+        let head = repo.head().of(prj.get_name())?;
+        let commit = head.peel_to_commit().of(prj.get_name())?.id().to_string();
+        let branch = head
+            .is_branch()
+            .then(|| head.shorthand().map(str::to_string))
+            .flatten();
+        let describe = repo
+            .describe(
+                git2::DescribeOptions::new()
+                    .describe_tags()
+                    .show_commit_oid_as_fallback(true),
+            )
+            .and_then(|e| e.format(None))
+            .ok();
+        let remote_url = doctor::remote_url(&repo);
+        let dirty = repo
+            .statuses(Some(
+                git2::StatusOptions::new()
+                    .include_untracked(false)
+                    .include_ignored(false),
+            ))
+            .of(prj.get_name())?
+            .iter()
+            .any(|e| e.status() != git2::Status::CURRENT);
+        let last_fetch = std::fs::metadata(repo.path().join("FETCH_HEAD"))
+            .and_then(|e| e.modified())
+            .ok();
+        let generations = info::generations(&self.get_dirs().old(), prj.get_dir())
+            .iter()
+            .filter_map(|path| {
+                Repository::open(path)
+                    .and_then(|old| Ok(old.head()?.peel_to_commit()?.id().to_string()))
+                    .ok()
+            })
+            .collect();
+        Ok(ProjectInfo {
+            name: prj.get_name().to_string(),
+            url: prj.get_url().to_string(),
+            ref_string: prj.get_ref_string().to_string(),
+            remote_url,
+            branch,
+            commit,
+            describe,
+            dirty,
+            last_fetch,
+            generations,
+            disk_usage: self.disk_usage(prj.get_name())?,
+            src,
+        })
+    }
+    /// Cross-check the store with the `src()`, `old()` and `git()` directories,
+    /// without changing anything. The findings are in the order in which
+    /// they should be repaired.
+    ///
+    /// When several projects use the same directory, the one whose url is the
+    /// remote of the sources keeps it, or else the first one in the store,
+    /// and only that one is checked further
+    /// # Errors
+    /// - Unable to read the directories
+    fn diagnose(&self) -> Result<Vec<Finding>, Self::Error> {
+        let dirs = self.get_dirs();
+        let mut findings: Vec<Finding> = doctor::entry_names(&dirs.git())?
+            .into_iter()
+            .map(Finding::LeftoverGit)
+            .collect();
+        let mut by_dir: Vec<(&str, Vec<&Self::Project>)> = vec![];
+        for prj in self.get_all() {
+            match by_dir.iter_mut().find(|(dir, _)| *dir == prj.get_dir()) {
+                Some((_, prjs)) => prjs.push(prj),
+                None => by_dir.push((prj.get_dir(), vec![prj])),
+            }
+        }
+        let mut checks = vec![];
+        for (dir, prjs) in by_dir {
+            let src = dirs.src().join(dir);
+            let remote = Repository::open(&src)
+                .ok()
+                .and_then(|repo| doctor::remote_url(&repo));
+            let kept = prjs
+                .iter()
+                .find(|prj| remote.as_deref() == Some(prj.get_url()))
+                .unwrap_or(&prjs[0]);
+            if prjs.len() > 1 {
+                findings.push(Finding::DuplicateDir(
+                    dir.to_string(),
+                    kept.get_name().to_string(),
+                    prjs.iter()
+                        .filter(|prj| prj.get_name() != kept.get_name())
+                        .map(|prj| prj.get_name().to_string())
+                        .collect(),
+                ));
+            }
+            if !src.exists() {
+                checks.push(Finding::MissingSources(kept.get_name().to_string()));
+            } else if !doctor::resolves(&src, kept.get_ref_string()) {
+                checks.push(Finding::UnresolvedRef(
+                    kept.get_name().to_string(),
+                    kept.get_ref_string().to_string(),
+                ));
+            }
+        }
+        findings.append(&mut checks);
+        for dir in doctor::entry_names(&dirs.src())? {
+            if self.get_store().check_dir_free(&dir) {
+                findings.push(Finding::OrphanSources(dir));
+            }
+        }
+        for dir in doctor::entry_names(&dirs.old())? {
+            if self.get_store().check_dir_free(info::generation_dir(&dir)) {
+                findings.push(Finding::OrphanOld(dir));
+            }
+        }
+        Ok(findings)
+    }
+    /// Apply the fix of a finding of [`Self::diagnose`], as described by
+    /// [`Finding::fix`]. The projects that are taken out of the store are
+    /// returned, so that they can be installed again in another directory
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - Cloning the project or switching to its reference
+    /// - Removing or moving the directories
+    /// - Building the project
+    /// - Removing projects from the store
+    fn repair(&mut self, finding: &Finding) -> Result<Vec<Self::Project>, Self::Error> {
+        let _span = info_span!("repair", %finding).entered();
+        let dirs = self.get_dirs();
+        match finding {
+            Finding::LeftoverGit(dir) => doctor::remove(&dirs.git().join(dir))?,
+            Finding::OrphanSources(dir) => doctor::remove(&dirs.src().join(dir))?,
+            Finding::OrphanOld(dir) => doctor::remove(&dirs.old().join(dir))?,
+            Finding::MissingSources(name) | Finding::UnresolvedRef(name, _) => {
+                let prj = self
+                    .get_one(name)
+                    .ok_or_else(|| CommonPMErrors::NonExisting(name.to_string()))?;
+                let (repo, git_dir) = self.download(prj)?;
+                if let Err(e) = self.switch_branch(prj, &repo) {
+                    std::fs::remove_dir_all(&git_dir).at("remove", &git_dir)?;
+                    return Err(e);
+                }
+                if dirs.src().join(prj.get_dir()).exists() {
+                    self.push_generation(prj)?;
+                }
+                self.mv(prj, &git_dir)?;
+                self.build(prj)?;
+            }
+            Finding::DuplicateDir(_, _, rest) => {
+                let mut removed = vec![];
+                for name in rest {
+                    let prj = self
+                        .get_one(name)
+                        .ok_or_else(|| CommonPMErrors::NonExisting(name.to_string()))?;
+                    removed.push(prj.clone());
+                    self.get_mut_store()
+                        .remove(name)
+                        .map_err(Self::map_store_error)?;
+                }
+                return Ok(removed);
+            }
+        }
+        info!(fix = finding.fix(), "Repaired");
+        Ok(vec![])
+    }
+    /// Install the newest previous version of a project given it's name. It
+    /// stops being a previous version, so restoring again goes one version
+    /// further back. As it replaces the installed version, the update hooks
+    /// are run around it. Until it's built, the installed version is kept as
+    /// the newest previous version, so if it fails neither of them is lost
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If there is no previous version -> `CommonPMErrors::NoPreviousVersion`
     /// - Unable to remove the src directory
     /// -  Unable to copy the directory from old to new
-    /// - Unable to build the project
+    /// - Unable to build the project or running its update hooks
     fn restore<T: AsRef<str>>(&self, prj_name: T) -> Result<(), Self::Error> {
+        let _span = info_span!("restore", project = prj_name.as_ref()).entered();
         let prj = self
             .get_one(prj_name.as_ref())
-            .ok_or(CommonPMErrors::NonExisting)?;
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?;
         let dir = prj.get_dir();
-        let old_dir = self.get_dirs().old().join(dir);
+        let old = self.get_dirs().old();
+        let generations = info::generations(&old, dir);
+        let newest = generations
+            .first()
+            .ok_or_else(|| CommonPMErrors::NoPreviousVersion(prj.get_name().to_string()))?;
         let src_dir = self.get_dirs().src().join(dir);
-        std::fs::remove_dir_all(&src_dir)?;
-        self.copy_directory(old_dir, &src_dir)?;
+        self.run_hook(prj, Hook::PreUpdate)?;
+        for (n, path) in generations.iter().enumerate().rev() {
+            std::fs::rename(path, info::generation(&old, dir, n + 1)).at("move", path)?;
+        }
+        self.copy_directory(&src_dir, newest)?;
+        let restored = info::generation(&old, dir, 1);
+        std::fs::remove_dir_all(&src_dir).at("remove", &src_dir)?;
+        self.copy_directory(&restored, &src_dir)?;
         self.build(prj)?;
+        self.run_hook(prj, Hook::PostUpdate)?;
+        for path in [newest, &restored] {
+            std::fs::remove_dir_all(path).at("remove", path)?;
+        }
+        for n in 1..generations.len() {
+            let path = info::generation(&old, dir, n + 1);
+            std::fs::rename(&path, info::generation(&old, dir, n - 1)).at("move", &path)?;
+        }
         Ok(())
     }
-    /// Substitute the contents of a project with name `prj_name` with the contents in `prj`
+    /// Restore several projects, going on after a project fails. Projects
+    /// without a previous version are skipped. They are restored after the
+    /// projects they depend on, and the report is in that order
+    /// # Errors
+    /// - If any of the projects isn't installed -> `CommonPMErrors::NonExisting`
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    ///
+    /// The errors from restoring each of the projects are in the report
+    fn restore_batch<T: AsRef<str>>(
+        &self,
+        prj_names: &[T],
+    ) -> Result<BatchReport<Self::Error>, Self::Error> {
+        let prjs = prj_names
+            .iter()
+            .map(|name| {
+                self.get_one(name.as_ref())
+                    .ok_or_else(|| CommonPMErrors::NonExisting(name.as_ref().to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let old = self.get_dirs().old();
+        let mut report = BatchReport::default();
+        for prj in deps::topological_order(&prjs)? {
+            let outcome = if info::generations(&old, prj.get_dir()).is_empty() {
+                Outcome::Skipped("there is no previous version".into())
+            } else {
+                match self.restore(prj.get_name()) {
+                    Ok(()) => Outcome::Done,
+                    Err(e) => Outcome::Failed(e),
+                }
+            };
+            report.push(prj.get_name(), outcome);
+        }
+        Ok(report)
+    }
+    /// Run the build script of all the projects that depend on the project with
+    /// name `prj_name`, in topological order
     /// # Errors
-    /// - Store error getting the project or substituting it.
+    /// - If the dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+    /// - Unable to build any of the projects
+    fn rebuild_dependents<T: AsRef<str>>(&self, prj_name: T) -> Result<(), Self::Error> {
+        for prj in self.get_dependents(prj_name)? {
+            self.build(prj)?;
+        }
+        Ok(())
+    }
+    /// Substitute the contents of a project with name `prj_name` with the contents in `prj`.
+    ///
+    /// If the directory changes, the directories of the project are moved. If
+    /// the url or the reference change, the repository follows them, leaving
+    /// the sources at the new reference, but without building them. When any
+    /// step fails the previous ones are undone
+    /// # Errors
+    /// - Unable to get the project -> `CommonPMErrors::NonExisting`
+    /// - If the new name or directory is used by other project -> `CommonPMErrors::AlreadyExisting`
+    /// - If it's renamed and other projects depend on it -> `CommonPMErrors::HasDependents`
+    /// - If any of its dependencies isn't installed -> `CommonPMErrors::MissingDependencies`
+    /// - If the new dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+    /// - Moving the directories or following the new url or reference
+    /// - Store error substituting the project.
     fn edit<T: AsRef<str>>(&mut self, prj_name: T, prj: Self::Project) -> Result<(), Self::Error> {
-        self.get_mut_store()
-            .edit(prj_name.as_ref(), prj)
-            .map_err(Self::map_store_error)?;
+        let old = self
+            .get_one(prj_name.as_ref())
+            .ok_or_else(|| CommonPMErrors::NonExisting(prj_name.as_ref().to_string()))?
+            .clone();
+        if old.get_name() != prj.get_name() && !self.get_store().check_name_free(prj.get_name()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_name().to_string()))?;
+        }
+        if old.get_name() != prj.get_name() {
+            // Their dependencies would still have the old name
+            let dependents = self.get_dependents(old.get_name())?;
+            if !dependents.is_empty() {
+                Err(CommonPMErrors::HasDependents(
+                    old.get_name().to_string(),
+                    dependents
+                        .iter()
+                        .map(|e| e.get_name().to_string())
+                        .collect(),
+                ))?;
+            }
+        }
+        if old.get_dir() != prj.get_dir() && !self.get_store().check_dir_free(prj.get_dir()) {
+            Err(CommonPMErrors::AlreadyExisting(prj.get_dir().to_string()))?;
+        }
+        self.check_dependencies(&prj)?;
+        let mut all: Vec<&Self::Project> = self
+            .get_store()
+            .iter()
+            .filter(|e| e.get_name() != prj_name.as_ref())
+            .collect();
+        all.push(&prj);
+        deps::topological_order(&all)?;
+        let moved = old.get_dir() != prj.get_dir();
+        if moved {
+            self.move_dirs(old.get_dir(), prj.get_dir())?;
+        }
+        if let Err(e) = self.follow_remote(&old, &prj) {
+            if moved {
+                self.move_dirs(prj.get_dir(), old.get_dir())?;
+            }
+            return Err(e);
+        }
+        if let Err(e) = self.get_mut_store().edit(prj_name.as_ref(), prj.clone()) {
+            if moved {
+                self.move_dirs(prj.get_dir(), old.get_dir())?;
+            }
+            self.follow_remote(&prj, &old)?;
+            return Err(Self::map_store_error(e));
+        }
         Ok(())
     }
     /// Get the project configuration, given it's name
@@ -316,6 +1450,91 @@ pub trait PMProgrammatic: PMOperations {
     fn get_all(&self) -> Vec<&Self::Project> {
         self.get_store().iter().collect()
     }
+    /// Get the projects that match a query, checking the build status that it
+    /// asks for with [`PMOperations::build_status`]
+    fn query(&self, query: &Query<Self::Project>) -> Vec<&Self::Project> {
+        self.get_store()
+            .query(query)
+            .into_iter()
+            .filter(|prj| query.selects_status(self.build_status(prj)))
+            .collect()
+    }
+    /// Get the projects that have any of the `tags`, in the order of the store
+    fn get_tagged<T: AsRef<str>>(&self, tags: &[T]) -> Vec<&Self::Project> {
+        if tags.is_empty() {
+            return vec![];
+        }
+        let query = tags
+            .iter()
+            .fold(Query::new(), |query, tag| query.tag(tag.as_ref()));
+        self.get_store().query(&query)
+    }
+    /// Get the projects that depend, directly or transitively, on the project
+    /// with name `prj_name`, in the order in which they should be built
+    /// # Errors
+    /// - If the dependencies form a cycle -> `CommonPMErrors::DependencyCycle`
+    fn get_dependents<T: AsRef<str>>(
+        &self,
+        prj_name: T,
+    ) -> Result<Vec<&Self::Project>, Self::Error> {
+        Ok(deps::dependents(&self.get_all(), prj_name.as_ref())?)
+    }
+    /// Get the projects that have to be installed to install `prjs`, in the
+    /// order in which they have to be installed. Those are `prjs` and their
+    /// dependencies, direct or transitive, that aren't installed, which are
+    /// taken from `prjs` or from `available`
+    /// # Errors
+    /// - If a dependency isn't installed, nor found in `prjs` or `available` -> `CommonPMErrors::MissingDependencies`
+    /// - If the projects depend on each other in a cycle -> `CommonPMErrors::DependencyCycle`
+    fn with_dependencies(
+        &self,
+        mut prjs: Vec<Self::Project>,
+        available: &[Self::Project],
+    ) -> Result<Vec<Self::Project>, Self::Error> {
+        let mut idx = 0;
+        while idx < prjs.len() {
+            let mut missing = vec![];
+            for dep in prjs[idx].get_dependencies().to_vec() {
+                if self.get_one(&dep).is_some() || prjs.iter().any(|e| e.get_name() == dep) {
+                    continue;
+                }
+                match available.iter().find(|e| e.get_name() == dep) {
+                    Some(found) => prjs.push(found.clone()),
+                    None => missing.push(dep),
+                }
+            }
+            if !missing.is_empty() {
+                Err(CommonPMErrors::MissingDependencies(
+                    prjs[idx].get_name().to_string(),
+                    missing,
+                ))?;
+            }
+            idx += 1;
+        }
+        let refs: Vec<&Self::Project> = prjs.iter().collect();
+        Ok(deps::topological_order(&refs)?
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+    /// Check that all the dependencies of a project are installed
+    /// # Errors
+    /// - If any of them isn't -> `CommonPMErrors::MissingDependencies`
+    fn check_dependencies(&self, prj: &Self::Project) -> Result<(), Self::Error> {
+        let missing: Vec<String> = prj
+            .get_dependencies()
+            .iter()
+            .filter(|dep| self.get_one(dep).is_none())
+            .cloned()
+            .collect();
+        if !missing.is_empty() {
+            Err(CommonPMErrors::MissingDependencies(
+                prj.get_name().to_string(),
+                missing,
+            ))?;
+        }
+        Ok(())
+    }
 }
 
 /// This trait defines methods for the six tasks to be interactive, it provides
@@ -347,16 +1566,27 @@ pub trait PMInteractive: PMProgrammatic {
     fn i_list<T: AsRef<str>, Q: AsRef<[T]>>(&self, prj_names: Q) -> Result<(), Self::Error>;
     /// Edit a projects information and store that
     fn i_edit<T: AsRef<str>>(&mut self, project: T) -> Result<(), Self::Error>;
-    /// Update the projects, (Possibly a forwarding of the `PMBasics` update
-    /// method applied to each of the projects)
-    fn i_update<T: AsRef<str>, Q: AsRef<[T]>>(&self, prj_names: Q) -> Result<(), Self::Error>;
-    /// Take an the last version of a project, set it as the current and build
-    /// and install it (Possibly just a forwarding of the `PMBasics` restore method)
-    fn i_restore<T: AsRef<str>, Q: AsRef<[T]>>(self, prj_names: Q) -> Result<(), Self::Error>;
-    /// Uninstall a project and delete the related information that the
-    /// project manager has about it. (Possibly a forwarding of the `PMBasics` uninstall method)
+    /// Update the projects, asking which ones when none are given. (Possibly a
+    /// forwarding of the `PMProgrammatic` update batch method). Returns what
+    /// happened to each of the projects
+    fn i_update<T: AsRef<str>, Q: AsRef<[T]>>(
+        &self,
+        prj_names: Q,
+    ) -> Result<BatchReport<Self::Error>, Self::Error>;
+    /// Take an the last version of the projects, set it as the current and
+    /// build and install them (Possibly just a forwarding of the
+    /// `PMProgrammatic` restore batch method). Returns what happened to each of
+    /// the projects
+    fn i_restore<T: AsRef<str>, Q: AsRef<[T]>>(
+        self,
+        prj_names: Q,
+    ) -> Result<BatchReport<Self::Error>, Self::Error>;
+    /// Uninstall the projects and delete the related information that the
+    /// project manager has about them. (Possibly a forwarding of the
+    /// `PMProgrammatic` uninstall batch method). Returns what happened to each
+    /// of the projects
     fn i_uninstall<T: AsRef<str>, Q: AsRef<[T]>>(
         &mut self,
         prj_names: Q,
-    ) -> Result<(), Self::Error>;
+    ) -> Result<BatchReport<Self::Error>, Self::Error>;
 }
@@ -1,6 +1,69 @@
 //! This couple of traits define how projects are stored and interacted with by
 //! project managers.
 
+use crate::Query;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What to do when updating a project
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UpdatePolicy {
+    /// Update the project to the newest version every time
+    Always,
+    /// Ask whether to update or not
+    Ask,
+    /// Do not update the repo, **default** value
+    #[default]
+    Never,
+}
+
+impl std::fmt::Display for UpdatePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Always => {
+                write!(f, "Always try to update the project")
+            }
+            Self::Ask => {
+                write!(f, "Ask wether ot update or not")
+            }
+            Self::Never => {
+                write!(f, "Never try to update the project")
+            }
+        }
+    }
+}
+
+/// How the install script of a project went the last time that it was run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildStatus {
+    /// The install script succeeded
+    Built,
+    /// The install script failed
+    Failed,
+    /// The install script hasn't been run on the current sources, or there
+    /// are no sources
+    NotBuilt,
+}
+
+/// The points in the life of a project in which extra scripts can be run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// After the sources are in place, before building a project for the first time
+    PreInstall,
+    /// After building a project for the first time
+    PostInstall,
+    /// After getting the new sources, before building them. The old sources are
+    /// still in place
+    PreUpdate,
+    /// After building the new sources
+    PostUpdate,
+    /// Before running the uninstall script
+    PreUninstall,
+    /// After the sources are removed. It is run from the parent of the sources
+    PostUninstall,
+}
+
 /// A trait that is used to know to set up a project
 pub trait ProjectIface: Clone {
     /// Get the name of the project
@@ -15,6 +78,31 @@ pub trait ProjectIface: Clone {
     fn get_install(&self) -> &[String];
     /// Get the uninstall script
     fn get_uninstall(&self) -> &[String];
+    /// Get the names of the projects that have to be installed before this one.
+    /// By default a project has no dependencies
+    fn get_dependencies(&self) -> &[String] {
+        &[]
+    }
+    /// Get the executables that have to be available to build the project, like
+    /// `cargo` or `meson>=0.60`. By default a project has no requirements
+    fn get_requirements(&self) -> &[String] {
+        &[]
+    }
+    /// Get the free-form tags that group the project with others, like
+    /// `editors` or `work`. By default a project has no tags
+    fn get_tags(&self) -> &[String] {
+        &[]
+    }
+    /// Get what to do when updating the project. By default it's never updated
+    /// unless asked to
+    fn get_update_policy(&self) -> UpdatePolicy {
+        UpdatePolicy::default()
+    }
+    /// Get the script to run at some point of the project's life. By default
+    /// there are no hooks
+    fn get_hook(&self, _hook: Hook) -> &[String] {
+        &[]
+    }
 }
 
 /// How to interact with however your projects are stored
@@ -54,7 +142,34 @@ where
         self.check_dir_free(dir) && self.check_name_free(prj_name)
     }
     /// Return an iterator over refereneces of Project Items
-    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &T> + 'a>;
+    fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a T> + 'a>;
     /// Check if there are elements in the store
     fn is_empty(&self) -> bool;
+    /// Get the projects that have the tag `tag`
+    fn tagged<'a>(&'a self, tag: &str) -> Vec<&'a T> {
+        self.query(&Query::new().tag(tag))
+    }
+    /// Get the projects that match a query, in the order it sets. By default
+    /// every project is checked
+    fn query<'a>(&'a self, query: &Query<T>) -> Vec<&'a T> {
+        query.run(self.iter())
+    }
+    /// Get the project that uses the directory `dir`, if any
+    fn find_by_dir<'a>(&'a self, dir: &str) -> Option<&'a T> {
+        self.iter().find(|prj| prj.get_dir() == dir)
+    }
+    /// Get the projects that are cloned from `url`. Trailing slashes and a
+    /// `.git` suffix are ignored when comparing the urls
+    fn find_by_url<'a>(&'a self, url: &str) -> Vec<&'a T> {
+        let url = normalize_url(url);
+        self.iter()
+            .filter(|prj| normalize_url(prj.get_url()) == url)
+            .collect()
+    }
+}
+
+/// Remove the parts of an url that don't change the repository it points to
+fn normalize_url(url: &str) -> &str {
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url)
 }
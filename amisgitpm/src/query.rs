@@ -0,0 +1,246 @@
+//! Queries to select projects from a [`ProjectStore`](crate::ProjectStore)
+//! without re-implementing the filtering every time.
+//!
+//! A [`Query`] is built by chaining conditions, all of which must hold, and it
+//! is run with [`ProjectStore::query`](crate::ProjectStore::query). Stores
+//! implement it by checking every project, but they can override it to use
+//! their own indices. How the projects were built isn't known by the stores,
+//! so queries on the [`BuildStatus`] are run with
+//! [`PMProgrammatic::query`](crate::PMProgrammatic::query) instead.
+//!
+//! ```
+//! # use amisgitpm::query::{Query, SortKey};
+//! # use amisgitpm::{ProjectIface, UpdatePolicy};
+//! # #[derive(Clone)]
+//! # struct Prj;
+//! # impl ProjectIface for Prj {
+//! #     fn get_name(&self) -> &str { "helix" }
+//! #     fn get_dir(&self) -> &str { "helix" }
+//! #     fn get_url(&self) -> &str { "https://github.com/helix-editor/helix.git" }
+//! #     fn get_ref_string(&self) -> &str { "refs/heads/master" }
+//! #     fn get_install(&self) -> &[String] { &[] }
+//! #     fn get_uninstall(&self) -> &[String] { &[] }
+//! # }
+//! let query = Query::new()
+//!     .host("github.com")
+//!     .update_policy(UpdatePolicy::Never)
+//!     .filter(|prj: &Prj| !prj.get_install().is_empty())
+//!     .sort(SortKey::Name);
+//! assert!(!query.matches(&Prj));
+//! ```
+
+use crate::{BuildStatus, ProjectIface, UpdatePolicy};
+
+/// What to sort the results of a query by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// The name of the projects
+    Name,
+    /// The directory of the projects
+    Dir,
+    /// The url of the projects
+    Url,
+}
+
+/// A condition on a project
+type Predicate<'q, T> = Box<dyn Fn(&T) -> bool + 'q>;
+
+/// A set of conditions that projects must meet, and the order of the results
+pub struct Query<'q, T> {
+    host: Option<String>,
+    tags: Vec<String>,
+    policies: Vec<UpdatePolicy>,
+    statuses: Vec<BuildStatus>,
+    predicates: Vec<Predicate<'q, T>>,
+    sort: Option<SortKey>,
+    reverse: bool,
+}
+
+impl<'q, T> Default for Query<'q, T> {
+    fn default() -> Self {
+        Self {
+            host: None,
+            tags: vec![],
+            policies: vec![],
+            statuses: vec![],
+            predicates: vec![],
+            sort: None,
+            reverse: false,
+        }
+    }
+}
+
+impl<'q, T: ProjectIface> Query<'q, T> {
+    /// A query that every project matches, in the order of the store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Only the projects whose url is in the host `host`, like `github.com`
+    pub fn host<S: Into<String>>(mut self, host: S) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+    /// Only the projects that have the tag `tag`. If called several times, the
+    /// projects that have any of the tags
+    pub fn tag<S: Into<String>>(mut self, tag: S) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+    /// Only the projects with the update policy `policy`. If called several
+    /// times, the projects that have any of the policies
+    pub fn update_policy(mut self, policy: UpdatePolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+    /// Only the projects whose last build ended with `status`. If called
+    /// several times, the projects with any of the statuses. It's only checked
+    /// by [`PMProgrammatic::query`](crate::PMProgrammatic::query)
+    pub fn build_status(mut self, status: BuildStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+    /// Only the projects for which `predicate` is true. This is how to select
+    /// by the fields that only some project types have
+    pub fn filter<F: Fn(&T) -> bool + 'q>(mut self, predicate: F) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+    /// Sort the results
+    pub fn sort(mut self, key: SortKey) -> Self {
+        self.sort = Some(key);
+        self
+    }
+    /// Give the results in the reverse order
+    pub fn reverse(mut self) -> Self {
+        self.reverse = !self.reverse;
+        self
+    }
+    /// Get the tags that the query selects, any of them
+    pub fn get_tags(&self) -> &[String] {
+        &self.tags
+    }
+    /// Whether a build status is one of those that the query selects
+    pub fn selects_status(&self, status: BuildStatus) -> bool {
+        self.statuses.is_empty() || self.statuses.contains(&status)
+    }
+    /// Whether a project meets all the conditions of the query, but its
+    /// build status
+    pub fn matches(&self, prj: &T) -> bool {
+        (self.host.is_none() || url_host(prj.get_url()) == self.host.as_deref())
+            && (self.tags.is_empty() || prj.get_tags().iter().any(|tag| self.tags.contains(tag)))
+            && (self.policies.is_empty() || self.policies.contains(&prj.get_update_policy()))
+            && self.predicates.iter().all(|predicate| predicate(prj))
+    }
+    /// Select and sort the projects that match the query
+    pub fn run<'a, I: IntoIterator<Item = &'a T>>(&self, prjs: I) -> Vec<&'a T>
+    where
+        T: 'a,
+    {
+        let mut results: Vec<&T> = prjs.into_iter().filter(|prj| self.matches(prj)).collect();
+        self.order(&mut results);
+        results
+    }
+    /// Sort some results as the query says
+    pub fn order(&self, results: &mut [&T]) {
+        match self.sort {
+            Some(SortKey::Name) => results.sort_by(|a, b| a.get_name().cmp(b.get_name())),
+            Some(SortKey::Dir) => results.sort_by(|a, b| a.get_dir().cmp(b.get_dir())),
+            Some(SortKey::Url) => results.sort_by(|a, b| a.get_url().cmp(b.get_url())),
+            None => {}
+        }
+        if self.reverse {
+            results.reverse();
+        }
+    }
+}
+
+/// Get the host of a git url, like `github.com` for both
+/// `https://github.com/user/repo.git` and `git@github.com:user/repo.git`.
+/// Local urls, like `file:///path/to/repo`, have no host
+pub fn url_host(url: &str) -> Option<&str> {
+    let rest = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        // scp like urls, user@host:path
+        None => url.split_once(':')?.0,
+    };
+    let authority = rest.split('/').next()?;
+    let host = authority.rsplit_once('@').map_or(authority, |(_, e)| e);
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Prj(&'static str, &'static str, Vec<String>, UpdatePolicy);
+
+    impl ProjectIface for Prj {
+        fn get_name(&self) -> &str {
+            self.0
+        }
+        fn get_dir(&self) -> &str {
+            self.0
+        }
+        fn get_url(&self) -> &str {
+            self.1
+        }
+        fn get_ref_string(&self) -> &str {
+            ""
+        }
+        fn get_install(&self) -> &[String] {
+            &[]
+        }
+        fn get_uninstall(&self) -> &[String] {
+            &[]
+        }
+        fn get_tags(&self) -> &[String] {
+            &self.2
+        }
+        fn get_update_policy(&self) -> UpdatePolicy {
+            self.3
+        }
+    }
+
+    #[test]
+    fn finds_hosts() {
+        assert_eq!(url_host("https://github.com/a/b.git"), Some("github.com"));
+        assert_eq!(url_host("ssh://git@gitlab.com:22/a/b"), Some("gitlab.com"));
+        assert_eq!(url_host("git@codeberg.org:a/b.git"), Some("codeberg.org"));
+        assert_eq!(url_host("file:///home/a/b"), None);
+    }
+
+    #[test]
+    fn filters_and_sorts() {
+        let prjs = [
+            Prj(
+                "c",
+                "https://github.com/c",
+                vec!["work".into()],
+                UpdatePolicy::Always,
+            ),
+            Prj(
+                "a",
+                "https://gitlab.com/a",
+                vec!["work".into()],
+                UpdatePolicy::Ask,
+            ),
+            Prj("b", "https://github.com/b", vec![], UpdatePolicy::Never),
+        ];
+        let names = |results: Vec<&Prj>| -> Vec<&str> { results.iter().map(|e| e.0).collect() };
+        let query = Query::new().host("github.com").sort(SortKey::Name);
+        assert_eq!(names(query.run(&prjs)), vec!["b", "c"]);
+        let query = Query::new().tag("work").sort(SortKey::Name).reverse();
+        assert_eq!(names(query.run(&prjs)), vec!["c", "a"]);
+        let query = Query::new().filter(|prj: &Prj| prj.0 != "c");
+        assert_eq!(names(query.run(&prjs)), vec!["a", "b"]);
+        let query = Query::new()
+            .update_policy(UpdatePolicy::Always)
+            .update_policy(UpdatePolicy::Ask);
+        assert_eq!(names(query.run(&prjs)), vec!["c", "a"]);
+        let query: Query<Prj> = Query::new().build_status(BuildStatus::Failed);
+        assert!(query.selects_status(BuildStatus::Failed));
+        assert!(!query.selects_status(BuildStatus::Built));
+    }
+}
@@ -0,0 +1,183 @@
+//! Requirements are executables that must be available in the host before
+//! building a project.
+//!
+//! They are written as a name, optionally followed by a minimum version,
+//! like `cargo` or `meson>=0.60`. The version of an executable is found by
+//! running it with `--version` and looking for the first version-like word in
+//! the output.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// An executable that has to be available, optionally with a minimum version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// The name of the executable, or a path to it
+    pub executable: String,
+    /// The minimum version that the executable must report
+    pub min_version: Option<String>,
+}
+
+impl std::str::FromStr for Requirement {
+    type Err = std::convert::Infallible;
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        Ok(match spec.split_once(">=") {
+            Some((executable, version)) => Self {
+                executable: executable.trim().to_string(),
+                min_version: Some(version.trim().to_string()),
+            },
+            None => Self {
+                executable: spec.trim().to_string(),
+                min_version: None,
+            },
+        })
+    }
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.min_version {
+            Some(version) => write!(f, "{}>={version}", self.executable),
+            None => write!(f, "{}", self.executable),
+        }
+    }
+}
+
+impl Requirement {
+    /// Find the executable in the `PATH`, or at the given path if it has a `/`
+    pub fn find(&self) -> Option<PathBuf> {
+        if self.executable.contains('/') {
+            let path = PathBuf::from(&self.executable);
+            return is_executable(&path).then_some(path);
+        }
+        std::env::split_paths(&std::env::var_os("PATH")?)
+            .map(|dir| dir.join(&self.executable))
+            .find(|path| is_executable(path))
+    }
+    /// Check whether the requirement is met in this host. If it isn't, get a
+    /// description of what is wrong
+    pub fn check(&self) -> Result<(), String> {
+        let path = self.find().ok_or_else(|| format!("{} (not found)", self))?;
+        let min_version = match &self.min_version {
+            Some(version) => version,
+            None => return Ok(()),
+        };
+        let wanted =
+            parse_version(min_version).ok_or_else(|| format!("{} (bad minimum version)", self))?;
+        let found = Command::new(path)
+            .arg("--version")
+            .output()
+            .ok()
+            .and_then(|out| {
+                let stdout = String::from_utf8_lossy(&out.stdout).to_string();
+                let stderr = String::from_utf8_lossy(&out.stderr).to_string();
+                find_version(&stdout).or_else(|| find_version(&stderr))
+            })
+            .ok_or_else(|| format!("{} (unknown version)", self))?;
+        if compare_versions(&found, &wanted).is_lt() {
+            let found: Vec<String> = found.iter().map(|e| e.to_string()).collect();
+            Err(format!("{} (found {})", self, found.join(".")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn parse_version(word: &str) -> Option<Vec<u64>> {
+    let word = word.trim().trim_start_matches('v');
+    let end = word
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(word.len());
+    let version: Vec<u64> = word[..end]
+        .split('.')
+        .map_while(|e| e.parse().ok())
+        .collect();
+    (!version.is_empty()).then_some(version)
+}
+
+fn find_version(output: &str) -> Option<Vec<u64>> {
+    output.split_whitespace().find_map(parse_version)
+}
+
+fn compare_versions(a: &[u64], b: &[u64]) -> std::cmp::Ordering {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|e| e.is_ne())
+        .unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// Check all the requirements given as specifications, returning the
+/// description of all those that are not met
+pub fn unmet<T: AsRef<str>>(specs: &[T]) -> Vec<String> {
+    specs
+        .iter()
+        .filter_map(|spec| {
+            let req: Requirement = spec.as_ref().parse().ok()?;
+            req.check().err()
+        })
+        .collect()
+}
+
+/// Whether the path is a file that can be run
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Whether the path is a file that can be run
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_specs() {
+        let req: Requirement = "meson >= 0.60".parse().unwrap();
+        assert_eq!(req.executable, "meson");
+        assert_eq!(req.min_version.as_deref(), Some("0.60"));
+        assert_eq!(req.to_string(), "meson>=0.60");
+        let req: Requirement = "cargo".parse().unwrap();
+        assert_eq!(req.min_version, None);
+    }
+
+    #[test]
+    fn finds_versions() {
+        assert_eq!(
+            find_version("cargo 1.65.0 (4bc8f24d3 2022-10-20)"),
+            Some(vec![1, 65, 0])
+        );
+        assert_eq!(find_version("git version v2.38.1"), Some(vec![2, 38, 1]));
+        assert_eq!(find_version("no version here"), None);
+        assert!(compare_versions(&[0, 60], &[0, 60, 0]).is_eq());
+        assert!(compare_versions(&[0, 59, 9], &[0, 60]).is_lt());
+    }
+
+    #[test]
+    fn checks_hosts() {
+        assert!(unmet(&["sh"]).is_empty());
+        assert_eq!(unmet(&["surely-not-an-executable-in-path"]).len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_files_that_cant_be_run() {
+        let file = std::env::temp_dir().join(format!("agpm-req-{}", std::process::id()));
+        std::fs::write(&file, "#!/bin/sh\n").unwrap();
+        let req = Requirement {
+            executable: file.to_string_lossy().to_string(),
+            min_version: None,
+        };
+        assert_eq!(req.find(), None);
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(req.find(), Some(file.clone()));
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+//! The changes that take the installed projects to a desired set of projects,
+//! as computed by [`PMProgrammatic::plan_sync`](crate::PMProgrammatic::plan_sync).
+
+use crate::ProjectIface;
+
+/// A change to the installed projects
+#[derive(Debug, Clone)]
+pub enum Change<P> {
+    /// Install a project that isn't installed, at a commit if given
+    Install(P, Option<String>),
+    /// Uninstall a project that isn't desired
+    Uninstall(P),
+    /// Get the sources of an installed project again, because its url,
+    /// reference, directory or locked commit changed
+    Repoint {
+        /// The installed project
+        from: P,
+        /// The desired project
+        to: P,
+        /// The commit to get, if locked
+        commit: Option<String>,
+    },
+    /// Change the configuration of an installed project, like its scripts,
+    /// and build it again
+    Edit {
+        /// The installed project
+        from: P,
+        /// The desired project
+        to: P,
+    },
+}
+
+impl<P: ProjectIface> Change<P> {
+    /// Get the name of the project that the change is about
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Install(prj, _) | Self::Uninstall(prj) => prj.get_name(),
+            Self::Repoint { to, .. } | Self::Edit { to, .. } => to.get_name(),
+        }
+    }
+}
+
+impl<P: ProjectIface> std::fmt::Display for Change<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Install(prj, commit) => {
+                write!(
+                    f,
+                    "+ {}: {} {}",
+                    prj.get_name(),
+                    prj.get_url(),
+                    prj.get_ref_string()
+                )?;
+                if let Some(commit) = commit {
+                    write!(f, " at {commit}")?;
+                }
+                Ok(())
+            }
+            Self::Uninstall(prj) => write!(f, "- {}", prj.get_name()),
+            Self::Repoint { from, to, commit } => {
+                write!(
+                    f,
+                    "~ {}: {} {} -> {} {}",
+                    to.get_name(),
+                    from.get_url(),
+                    from.get_ref_string(),
+                    to.get_url(),
+                    to.get_ref_string()
+                )?;
+                if let Some(commit) = commit {
+                    write!(f, " at {commit}")?;
+                }
+                Ok(())
+            }
+            Self::Edit { to, .. } => write!(f, "* {}: configuration changed", to.get_name()),
+        }
+    }
+}
@@ -1,64 +1,83 @@
-use agpm_interactions::Interactor;
+use agpm_dirs::TempDirs;
+use agpm_interactions::{InstallPresets, InteractError, Interactor};
 use agpm_pm::PrjManager;
 use agpm_project::{Project, UpdatePolicy};
-use agpm_store::Store;
-use amisgitpm::Directories;
+use agpm_store::MemoryStore;
+use amisgitpm::{Directories, PMOperations};
 use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use subprocess::Exec;
-use thiserror::Error;
 
-type TestInteracts = Interactor<TestDirs>;
-type TestProjectsStore = Store<TestDirs, Project>;
-type TestProjectManager = PrjManager<Project, TestDirs, TestProjectsStore, TestInteracts>;
+// Every manager gets its own temporary directories and store, so the tests
+// don't interfere with each other
+type TestInteracts = Interactor<TempDirs>;
+type TestProjectsStore = MemoryStore<Project>;
+type TestProjectManager = PrjManager<Project, TempDirs, TestProjectsStore, TestInteracts>;
 
-#[derive(Debug, Error)]
-enum EmptyError {}
+/// A git repository with a `dates.txt` file in its `main` branch, for the tests
+/// to install projects from. It's in its own temporary directory, that is
+/// removed when it's dropped
+struct FixtureRepo {
+    dirs: TempDirs,
+}
 
-struct TestDirs {}
-impl Directories for TestDirs {
-    type Error = EmptyError;
-    fn new() -> Result<Self, Self::Error> {
-        Ok(Self {})
+impl FixtureRepo {
+    fn new() -> Self {
+        let repo = Self {
+            dirs: TempDirs::new().unwrap(),
+        };
+        repo.run("git init -b main . && date +%s%N > dates.txt && git add dates.txt && git commit -m start");
+        repo
     }
-    fn projects_db(&self) -> PathBuf {
-        Path::new("../test_sandbox/config/projects").to_path_buf()
+    fn path(&self) -> &Path {
+        self.dirs.root()
     }
-    fn src(&self) -> PathBuf {
-        Path::new("../test_sandbox/cache/src").to_path_buf()
+    fn url(&self) -> String {
+        format!("file://{}", self.path().display())
     }
-    fn git(&self) -> PathBuf {
-        Path::new("../test_sandbox/cache/git").to_path_buf()
+    /// A project that follows the `main` branch, whose directory is its name
+    fn project(&self, name: &str) -> Project {
+        Project {
+            name: name.into(),
+            dir: name.into(),
+            url: self.url(),
+            ref_string: "refs/heads/main".into(),
+            ..Default::default()
+        }
     }
-    fn old(&self) -> PathBuf {
-        Path::new("../test_sandbox/cache/old").to_path_buf()
+    /// Commit a new `dates.txt` to the `main` branch
+    fn update(&self) {
+        self.run("date +%s%N > dates.txt && git commit -am update");
     }
-}
-
-/// This is needed because the interactions crate is imported with the feature
-/// suggestions
-impl agpm_suggestions::SuggestionsDirs for TestDirs {
-    fn suggestions(&self) -> PathBuf {
-        Path::new("../test_sandbox/config/suggestions").to_path_buf()
+    /// Create the branch `name`, with a `{name}.txt` file that `main` doesn't have
+    fn branch(&self, name: &str) {
+        self.run(&format!(
+            "git checkout -b {name} && date +%s%N > {name}.txt && git add {name}.txt && git commit -m {name} && git checkout main"
+        ));
+    }
+    fn run(&self, script: &str) {
+        assert!(Exec::shell(script)
+            .cwd(self.path())
+            .join()
+            .unwrap()
+            .success());
     }
 }
 
-impl TestDirs {
-    fn bin(&self) -> PathBuf {
-        Path::new("../test_sandbox/bin").to_path_buf()
-    }
+/// A new manager, and a new repository to install projects from
+fn setup() -> (TestProjectManager, FixtureRepo) {
+    (TestProjectManager::new().unwrap(), FixtureRepo::new())
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
     use agpm_pm::PMError;
-    use amisgitpm::{PMOperations, PMProgrammatic};
+    use amisgitpm::{BuildStatus, Change, Finding, Outcome, PMProgrammatic, ProjectStore, Query};
 
     #[test]
     fn install_uninstall_project() {
         let mut pm = TestProjectManager::new().unwrap();
-        pm.get_dirs().projects_db();
         let prj = Project {
             name: "Hello-crate".into(),
             dir: "Hello-crate".into(),
@@ -67,38 +86,34 @@ mod tests {
             update_policy: UpdatePolicy::Always,
             install_script: vec!["cargo install --path . --root ../../..".into()],
             uninstall_script: vec!["cargo uninstall --root ../../..".into()],
+            ..Default::default()
         };
         pm.install(prj.clone()).unwrap();
-        assert!(TestDirs::new()
-            .unwrap()
-            .bin()
+        assert!(pm
+            .get_dirs()
+            .root()
+            .join("bin")
             .join("rust-hello-world")
             .exists());
-        assert!(
-            if let Err(PMError::Common(amisgitpm::CommonPMErrors::AlreadyExisting)) =
-                pm.install(prj.clone())
-            {
-                true
-            } else {
-                false
-            }
-        );
+        assert!(matches!(
+            pm.install(prj.clone()),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::AlreadyExisting(
+                _
+            )))
+        ));
         pm.uninstall(&prj.name).unwrap();
     }
 
     #[test]
     fn updates() {
         let dir = std::fs::canonicalize(Path::new("./projects/git_upd2")).unwrap();
-        assert_eq!(
-            Exec::shell("bash 0_start.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 0_start.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         let mut url: String = "file://".into();
-        url.push_str(&dir.to_str().unwrap());
+        url.push_str(dir.to_str().unwrap());
         let prj = Project {
             name: "git_upd2".into(),
             dir: "git_upd2".into(),
@@ -107,6 +122,7 @@ mod tests {
             update_policy: UpdatePolicy::Always,
             install_script: vec![],
             uninstall_script: vec![],
+            ..Default::default()
         };
         let mut pm = TestProjectManager::new().unwrap();
         pm.install(prj).unwrap();
@@ -117,14 +133,11 @@ mod tests {
             .unwrap();
         let epoch = epoch.trim().parse::<i64>().unwrap();
         std::thread::sleep(std::time::Duration::from_secs(1));
-        assert_eq!(
-            Exec::shell("bash 1_update.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 1_update.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         let mut epoch2 = String::new();
         std::fs::File::open(dir.join("dates.txt"))
             .unwrap()
@@ -134,42 +147,30 @@ mod tests {
         assert!(epoch2 > epoch);
         pm.update("git_upd2").unwrap();
         let mut epoch2 = String::new();
-        std::fs::File::open(
-            TestDirs::new()
-                .unwrap()
-                .src()
-                .join("git_upd2")
-                .join("dates.txt"),
-        )
-        .unwrap()
-        .read_to_string(&mut epoch2)
-        .unwrap();
+        std::fs::File::open(pm.get_dirs().src().join("git_upd2").join("dates.txt"))
+            .unwrap()
+            .read_to_string(&mut epoch2)
+            .unwrap();
         let epoch2 = epoch2.trim().parse::<i64>().unwrap();
         assert!(epoch2 > epoch);
-        assert_eq!(
-            Exec::shell("bash 2_finish.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 2_finish.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         pm.uninstall("git_upd2").unwrap();
     }
 
     #[test]
     fn update_downgrade() {
         let dir = std::fs::canonicalize(Path::new("./projects/git_upd")).unwrap();
-        assert_eq!(
-            Exec::shell("bash 0_start.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 0_start.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         let mut url: String = "file://".into();
-        url.push_str(&dir.to_str().unwrap());
+        url.push_str(dir.to_str().unwrap());
         let prj = Project {
             name: "git_upd".into(),
             dir: "git_upd".into(),
@@ -178,6 +179,7 @@ mod tests {
             update_policy: UpdatePolicy::Always,
             install_script: vec![],
             uninstall_script: vec![],
+            ..Default::default()
         };
         let mut pm = TestProjectManager::new().unwrap();
         pm.install(prj).unwrap();
@@ -188,14 +190,11 @@ mod tests {
             .unwrap();
         let epoch = epoch.trim().parse::<i64>().unwrap();
         std::thread::sleep(std::time::Duration::from_secs(1));
-        assert_eq!(
-            Exec::shell("bash 1_update.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 1_update.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         let mut epoch2 = String::new();
         std::fs::File::open(dir.join("dates.txt"))
             .unwrap()
@@ -205,57 +204,46 @@ mod tests {
         assert!(epoch2 > epoch);
         pm.update("git_upd").unwrap();
         let mut epoch2 = String::new();
-        std::fs::File::open(
-            TestDirs::new()
-                .unwrap()
-                .src()
-                .join("git_upd")
-                .join("dates.txt"),
-        )
-        .unwrap()
-        .read_to_string(&mut epoch2)
-        .unwrap();
+        std::fs::File::open(pm.get_dirs().src().join("git_upd").join("dates.txt"))
+            .unwrap()
+            .read_to_string(&mut epoch2)
+            .unwrap();
         let epoch2 = epoch2.trim().parse::<i64>().unwrap();
         assert!(epoch2 > epoch);
         let mut epoch3 = String::new();
         pm.restore("git_upd").unwrap();
-        std::fs::File::open(
-            TestDirs::new()
-                .unwrap()
-                .src()
-                .join("git_upd")
-                .join("dates.txt"),
-        )
-        .unwrap()
-        .read_to_string(&mut epoch3)
-        .unwrap();
+        std::fs::File::open(pm.get_dirs().src().join("git_upd").join("dates.txt"))
+            .unwrap()
+            .read_to_string(&mut epoch3)
+            .unwrap();
         let epoch3 = epoch3.trim().parse::<i64>().unwrap();
         assert!(epoch3 < epoch2);
         assert_eq!(epoch3, epoch);
-        assert_eq!(
-            Exec::shell("bash 2_finish.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        // Only one generation is kept by default, and it was just restored
+        assert!(matches!(
+            pm.restore("git_upd"),
+            Err(PMError::Common(
+                amisgitpm::CommonPMErrors::NoPreviousVersion(_)
+            ))
+        ));
+        assert!(Exec::shell("bash 2_finish.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         pm.uninstall("git_upd").unwrap();
     }
 
     #[test]
     fn get_one_get_many_edit() {
         let dir = std::fs::canonicalize(Path::new("./projects/setable")).unwrap();
-        assert_eq!(
-            Exec::shell("bash 0_start.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
-        );
+        assert!(Exec::shell("bash 0_start.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
         let mut url: String = "file://".into();
-        url.push_str(&dir.to_str().unwrap());
+        url.push_str(dir.to_str().unwrap());
         let mut prj = Project {
             name: "a".into(),
             dir: "a".into(),
@@ -264,6 +252,7 @@ mod tests {
             update_policy: UpdatePolicy::Always,
             install_script: vec![],
             uninstall_script: vec![],
+            ..Default::default()
         };
         let mut pm = TestProjectManager::new().unwrap();
         pm.install(prj.clone()).unwrap();
@@ -276,7 +265,7 @@ mod tests {
         pm.get_one("a").unwrap();
         pm.get_one("b").unwrap();
         pm.get_one("c").unwrap();
-        assert!(pm.get_all().len() >= 3); // because they are in parallel, we can only be sure that is greater than three
+        assert_eq!(pm.get_all().len(), 3);
         prj.name = "d".into();
         prj.dir = "a".into();
         pm.edit("a", prj).unwrap();
@@ -284,13 +273,658 @@ mod tests {
         pm.uninstall("d").unwrap();
         pm.uninstall("b").unwrap();
         pm.uninstall("c").unwrap();
+        assert!(Exec::shell("bash 2_finish.sh")
+            .cwd(&dir)
+            .join()
+            .unwrap()
+            .success());
+    }
+
+    #[test]
+    fn dependencies() {
+        let (mut pm, repo) = setup();
+        let base = repo.project("dep_base");
+        let lib = Project {
+            name: "dep_lib".into(),
+            dir: "dep_lib".into(),
+            dependencies: vec!["dep_base".into()],
+            ..base.clone()
+        };
+        let app = Project {
+            name: "dep_app".into(),
+            dir: "dep_app".into(),
+            dependencies: vec!["dep_lib".into()],
+            ..base.clone()
+        };
+        assert!(matches!(
+            pm.install(app.clone()),
+            Err(PMError::Common(
+                amisgitpm::CommonPMErrors::MissingDependencies(_, _)
+            ))
+        ));
+        // Nothing is installed when a dependency can't be found anywhere
+        assert!(matches!(
+            pm.install_many(vec![app.clone(), lib.clone()]),
+            Err(PMError::Common(
+                amisgitpm::CommonPMErrors::MissingDependencies(_, _)
+            ))
+        ));
+        assert!(pm.get_one("dep_lib").is_none());
+        pm.install_with_dependencies(app, &[lib, base]).unwrap();
+        assert!(pm.get_one("dep_base").is_some());
+        assert!(pm.get_one("dep_lib").is_some());
+        assert!(matches!(
+            pm.uninstall("dep_base"),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::HasDependents(
+                _,
+                _
+            )))
+        ));
+        let renamed = Project {
+            name: "dep_base2".into(),
+            ..pm.get_one("dep_base").unwrap().clone()
+        };
+        assert!(matches!(
+            pm.edit("dep_base", renamed),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::HasDependents(
+                _,
+                _
+            )))
+        ));
+        assert!(pm.get_one("dep_base").is_some());
+        let mut cyclic = pm.get_one("dep_base").unwrap().clone();
+        cyclic.dependencies = vec!["dep_app".into()];
+        assert!(matches!(
+            pm.edit("dep_base", cyclic),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::DependencyCycle(
+                _
+            )))
+        ));
+        pm.uninstall_cascade("dep_base").unwrap();
+        assert!(pm.get_one("dep_lib").is_none());
+        assert!(pm.get_one("dep_app").is_none());
+    }
+
+    #[test]
+    fn install_batch() {
+        let (mut pm, repo) = setup();
+        let installed = repo.project("batch_a");
+        let new = Project {
+            name: "batch_b".into(),
+            dir: "batch_b".into(),
+            ..installed.clone()
+        };
+        let broken = Project {
+            name: "batch_c".into(),
+            dir: "batch_c".into(),
+            dependencies: vec!["batch_not_installed".into()],
+            ..installed.clone()
+        };
+        pm.install(installed.clone()).unwrap();
+        let report = pm.install_batch(vec![installed, new, broken]).unwrap();
+        assert!(matches!(report.outcomes[0].1, Outcome::Skipped(_)));
+        assert!(matches!(report.outcomes[1].1, Outcome::Done));
+        assert_eq!(report.failed(), vec!["batch_c"]);
+        pm.uninstall("batch_a").unwrap();
+        pm.uninstall("batch_b").unwrap();
+    }
+
+    #[test]
+    fn locked_install() {
+        let (mut pm, repo) = setup();
+        let prj = repo.project("locked");
+        pm.install(prj.clone()).unwrap();
+        let commit = pm.get_commit(&prj).unwrap();
+        repo.update();
+        pm.uninstall("locked").unwrap();
+        pm.install(prj.clone()).unwrap();
+        assert_ne!(pm.get_commit(&prj).unwrap(), commit);
+        pm.uninstall("locked").unwrap();
+        pm.install_at(prj.clone(), Some(&commit)).unwrap();
+        assert_eq!(pm.get_commit(&prj).unwrap(), commit);
+        pm.uninstall("locked").unwrap();
+        assert!(matches!(
+            pm.install_at(
+                prj.clone(),
+                Some("0123456789abcdef0123456789abcdef01234567")
+            ),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::MissingCommit(
+                _,
+                _
+            )))
+        ));
+        assert!(!pm.get_dirs().git().join("locked").exists());
+        // The download is removed too when the branch isn't there
+        let missing = Project {
+            ref_string: "refs/heads/missing".into(),
+            ..prj.clone()
+        };
+        assert!(pm.install(missing).is_err());
+        assert!(!pm.get_dirs().git().join("locked").exists());
+        pm.install(prj).unwrap();
+        pm.uninstall("locked").unwrap();
+    }
+
+    #[test]
+    fn sync() {
+        let (mut pm, repo) = setup();
+        let kept = repo.project("sync_a");
+        let removed = Project {
+            name: "sync_b".into(),
+            dir: "sync_b".into(),
+            ..kept.clone()
+        };
+        let moved = Project {
+            name: "sync_c".into(),
+            dir: "sync_c".into(),
+            ..kept.clone()
+        };
+        pm.install_many(vec![kept.clone(), removed, moved.clone()])
+            .unwrap();
+        let edited = Project {
+            install_script: vec!["touch built".into()],
+            ..kept
+        };
+        let moved = Project {
+            dir: "sync_c2".into(),
+            ..moved
+        };
+        let new = Project {
+            name: "sync_d".into(),
+            dir: "sync_d".into(),
+            ..edited.clone()
+        };
+        let desired = vec![
+            (edited.clone(), None),
+            (moved.clone(), None),
+            (new.clone(), None),
+        ];
+        let changes = pm.plan_sync(desired.clone()).unwrap();
+        assert_eq!(changes.len(), 4);
+        assert!(matches!(&changes[0], Change::Uninstall(prj) if prj.name == "sync_b"));
+        assert!(matches!(&changes[1], Change::Edit { .. }));
+        assert!(matches!(&changes[2], Change::Repoint { .. }));
+        assert!(matches!(&changes[3], Change::Install(..)));
+        assert!(!pm.apply_sync(changes).has_failures());
+        let src = pm.get_dirs().src();
+        assert!(pm.get_one("sync_b").is_none());
+        assert!(src.join("sync_a").join("built").exists());
+        assert!(src.join("sync_c2").exists());
+        assert!(!src.join("sync_c").exists());
+        // The repoint can be undone, from the new directory
+        assert!(pm.get_dirs().old().join("sync_c2").exists());
+        assert!(!pm.get_dirs().old().join("sync_c").exists());
+        assert!(src.join("sync_d").join("built").exists());
+        assert!(pm.plan_sync(desired).unwrap().is_empty());
+        pm.restore("sync_c").unwrap();
+        assert!(src.join("sync_c2").exists());
+        pm.uninstall("sync_a").unwrap();
+        pm.uninstall("sync_c").unwrap();
+        pm.uninstall("sync_d").unwrap();
+    }
+
+    #[test]
+    fn edit_moves_and_follows() {
+        let (mut pm, repo) = setup();
+        repo.branch("other");
+        let prj = repo.project("edit_a");
+        let other = Project {
+            name: "edit_c".into(),
+            dir: "edit_c".into(),
+            ..prj.clone()
+        };
+        pm.install(prj.clone()).unwrap();
+        pm.install(other).unwrap();
+        let src = pm.get_dirs().src();
+        let moved = Project {
+            dir: "edit_b".into(),
+            ..prj.clone()
+        };
+        pm.edit("edit_a", moved.clone()).unwrap();
+        assert!(src.join("edit_b").exists());
+        assert!(!src.join("edit_a").exists());
+        let taken = Project {
+            dir: "edit_c".into(),
+            ..prj
+        };
+        assert!(matches!(
+            pm.edit("edit_a", taken),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::AlreadyExisting(
+                _
+            )))
+        ));
+        let branched = Project {
+            ref_string: "refs/heads/other".into(),
+            ..moved.clone()
+        };
+        pm.edit("edit_a", branched.clone()).unwrap();
+        assert!(src.join("edit_b").join("other.txt").exists());
+        let unreachable = Project {
+            url: "file:///surely/not/a/repo".into(),
+            ref_string: "refs/heads/main".into(),
+            ..branched.clone()
+        };
+        assert!(pm.edit("edit_a", unreachable).is_err());
+        assert_eq!(pm.get_one("edit_a").unwrap(), &branched);
+        pm.edit("edit_a", moved).unwrap();
+        assert!(!src.join("edit_b").join("other.txt").exists());
+        pm.uninstall("edit_a").unwrap();
+        pm.uninstall("edit_c").unwrap();
+    }
+
+    #[test]
+    fn tags() {
+        let (mut pm, repo) = setup();
+        let editor = Project {
+            tags: vec!["tags_editors".into(), "tags_work".into()],
+            ..repo.project("tags_editor")
+        };
+        let daemon = Project {
+            name: "tags_daemon".into(),
+            dir: "tags_daemon".into(),
+            tags: vec!["tags_daemons".into()],
+            ..editor.clone()
+        };
+        pm.install_many(vec![editor, daemon]).unwrap();
+        let names =
+            |prjs: Vec<&Project>| -> Vec<String> { prjs.iter().map(|e| e.name.clone()).collect() };
+        assert_eq!(names(pm.get_tagged(&["tags_work"])), vec!["tags_editor"]);
+        let mut both = names(pm.get_tagged(&["tags_editors", "tags_daemons"]));
+        both.sort();
+        assert_eq!(both, vec!["tags_daemon", "tags_editor"]);
+        assert!(pm.get_tagged(&["tags_none"]).is_empty());
+        pm.uninstall("tags_editor").unwrap();
+        pm.uninstall("tags_daemon").unwrap();
+    }
+
+    #[test]
+    fn outdated_and_update_batch() {
+        let (mut pm, repo) = setup();
+        let first = repo.project("outdated_a");
+        let second = Project {
+            name: "outdated_b".into(),
+            dir: "outdated_b".into(),
+            ..first.clone()
+        };
+        pm.install_many(vec![first.clone(), second]).unwrap();
+        assert!(pm.outdated("outdated_a").unwrap().is_none());
+        let usage = pm.disk_usage("outdated_a").unwrap();
+        assert!(usage.src > 0);
+        assert_eq!(usage.old, 0);
+        repo.update();
+        let (installed, latest) = pm.outdated("outdated_a").unwrap().unwrap();
+        assert_eq!(installed, pm.get_commit(&first).unwrap());
+        assert_ne!(installed, latest);
+        let report = pm.update_batch(&["outdated_a"]).unwrap();
+        assert!(matches!(report.outcomes[0].1, Outcome::Done));
+        assert_eq!(pm.get_commit(&first).unwrap(), latest);
+        assert!(pm.outdated("outdated_a").unwrap().is_none());
+        assert!(pm.disk_usage("outdated_a").unwrap().old > 0);
+        // A restore that fails to build loses neither version
+        let failing = Project {
+            install_script: vec!["exit 1".into()],
+            ..first.clone()
+        };
+        pm.edit("outdated_a", failing).unwrap();
+        assert!(pm.restore("outdated_a").is_err());
+        let old = pm.get_dirs().old();
+        assert!(old.join("outdated_a").exists() && old.join("outdated_a~1").exists());
+        pm.edit("outdated_a", first.clone()).unwrap();
+        pm.restore("outdated_a").unwrap();
+        assert_eq!(pm.get_commit(&first).unwrap(), latest);
+        assert!(old.join("outdated_a").exists() && !old.join("outdated_a~1").exists());
+        let report = pm.update_batch(&["outdated_a", "outdated_b"]).unwrap();
+        let outcome = |name: &str| &report.outcomes.iter().find(|e| e.0 == name).unwrap().1;
+        assert!(matches!(outcome("outdated_a"), Outcome::Skipped(_)));
+        assert!(matches!(outcome("outdated_b"), Outcome::Done));
+        pm.uninstall("outdated_a").unwrap();
+        pm.uninstall("outdated_b").unwrap();
+    }
+
+    #[test]
+    fn info() {
+        let (mut pm, repo) = setup();
+        let prj = repo.project("info");
+        pm.install(prj.clone()).unwrap();
+        let info = pm.info("info").unwrap();
+        let first = pm.get_commit(&prj).unwrap();
+        assert_eq!(info.commit, first);
+        assert_eq!(info.branch.as_deref(), Some("main"));
+        assert!(!info.dirty);
+        assert!(info.remote_matches());
+        assert!(info.generations.is_empty());
+        repo.update();
+        pm.update("info").unwrap();
+        let info = pm.info("info").unwrap();
+        assert_ne!(info.commit, first);
+        assert_eq!(info.generations, vec![first]);
+        assert!(info.last_fetch.is_some());
+        std::fs::write(info.src.join("dates.txt"), "changed").unwrap();
+        assert!(pm.info("info").unwrap().dirty);
+        pm.uninstall("info").unwrap();
+    }
+
+    #[test]
+    fn doctor() {
+        let (mut pm, repo) = setup();
+        let prj = repo.project("doctor");
+        pm.install(prj.clone()).unwrap();
+        assert!(pm.diagnose().unwrap().is_empty());
+        let (src, old, git) = (
+            pm.get_dirs().src(),
+            pm.get_dirs().old(),
+            pm.get_dirs().git(),
+        );
+        std::fs::create_dir_all(git.join("leftover")).unwrap();
+        std::fs::create_dir_all(src.join("orphan")).unwrap();
+        std::fs::create_dir_all(old.join("orphan_old")).unwrap();
+        std::fs::remove_dir_all(src.join("doctor")).unwrap();
+        let twin = Project {
+            name: "doctor_twin".into(),
+            url: "file:///nowhere".into(),
+            ..prj.clone()
+        };
+        pm.get_mut_store().add(twin.clone()).unwrap();
+        let findings = pm.diagnose().unwrap();
         assert_eq!(
-            Exec::shell("bash 2_finish.sh")
-                .cwd(&dir)
-                .join()
-                .unwrap()
-                .success(),
-            true
+            findings,
+            vec![
+                Finding::LeftoverGit("leftover".into()),
+                Finding::DuplicateDir("doctor".into(), "doctor".into(), vec!["doctor_twin".into()]),
+                Finding::MissingSources("doctor".into()),
+                Finding::OrphanSources("orphan".into()),
+                Finding::OrphanOld("orphan_old".into()),
+            ]
         );
+        let mut removed = vec![];
+        for finding in findings.iter() {
+            removed.append(&mut pm.repair(finding).unwrap());
+        }
+        assert_eq!(removed, vec![twin]);
+        assert!(pm.diagnose().unwrap().is_empty());
+        let first = pm.get_commit(&prj).unwrap();
+        std::fs::remove_file(src.join("doctor").join(".git/refs/heads/main")).unwrap();
+        let broken = Finding::UnresolvedRef("doctor".into(), "refs/heads/main".into());
+        assert_eq!(pm.diagnose().unwrap(), vec![broken.clone()]);
+        pm.repair(&broken).unwrap();
+        assert_eq!(pm.get_commit(&prj).unwrap(), first);
+        assert!(old.join("doctor").exists());
+        assert!(pm.diagnose().unwrap().is_empty());
+        pm.uninstall("doctor").unwrap();
+    }
+
+    #[test]
+    fn install_with_presets() {
+        let (mut pm, repo) = setup();
+        let url = repo.url();
+        let presets = InstallPresets {
+            name: Some("presets_a".into()),
+            dir: Some("presets_a".into()),
+            ref_string: Some("main".into()),
+            update_policy: Some(UpdatePolicy::Always),
+            install_script: Some(vec!["touch built".into()]),
+            uninstall_script: Some(vec![]),
+            dependencies: Some(vec![]),
+            tags: Some(vec!["presets".into()]),
+            requirements: Some(vec![]),
+            ..Default::default()
+        };
+        pm.i_install_with(&url, presets.clone(), &[]).unwrap();
+        let prj = pm.get_one("presets_a").unwrap();
+        assert_eq!(prj.ref_string, "refs/heads/main");
+        assert_eq!(prj.update_policy, UpdatePolicy::Always);
+        assert_eq!(prj.tags, vec!["presets"]);
+        assert!(pm.get_dirs().src().join("presets_a").join("built").exists());
+        match pm.i_install_with(&url, presets.clone(), &[]) {
+            Err(e @ PMError::Interact(InteractError::Taken(_))) => {
+                assert!(e.hint().unwrap().contains("presets_a"));
+            }
+            other => panic!("Expected the name to be taken, got {other:?}"),
+        }
+        let suggested = InstallPresets {
+            name: Some("presets_b".into()),
+            dir: Some("presets_b".into()),
+            install_script: None,
+            accept_suggestion: Some(1),
+            ..presets.clone()
+        };
+        assert!(matches!(
+            pm.i_install_with(&url, suggested, &[]),
+            Err(PMError::Interact(InteractError::NoSuggestion(1, 0)))
+        ));
+        let unmet = InstallPresets {
+            name: Some("presets_c".into()),
+            dir: Some("presets_c".into()),
+            requirements: Some(vec!["surely-not-an-executable-in-path".into()]),
+            ..presets
+        };
+        assert!(matches!(
+            pm.i_install_with(&url, unmet, &[]),
+            Err(PMError::Common(
+                amisgitpm::CommonPMErrors::MissingRequirements(..)
+            ))
+        ));
+        assert!(!pm.get_dirs().git().join("presets_c").exists());
+        pm.uninstall("presets_a").unwrap();
+    }
+
+    #[test]
+    fn hooks() {
+        let (mut pm, repo) = setup();
+        let prj = Project {
+            hooks: agpm_project::Hooks {
+                pre_install: vec!["touch pre_install".into()],
+                post_install: vec!["test -f pre_install".into(), "touch post_install".into()],
+                post_uninstall: vec!["touch hooks_post_uninstall".into()],
+                ..Default::default()
+            },
+            ..repo.project("hooks")
+        };
+        pm.install(prj).unwrap();
+        let src = pm.get_dirs().src();
+        assert!(src.join("hooks").join("post_install").exists());
+        let failed = Query::new().build_status(BuildStatus::Failed);
+        assert!(pm.query(&failed).is_empty());
+        let mut failing = pm.get_one("hooks").unwrap().clone();
+        failing.install_script = vec!["exit 1".into()];
+        pm.edit("hooks", failing).unwrap();
+        match pm.rebuild("hooks") {
+            Err(e @ PMError::Exec(..)) => {
+                assert!(e.to_string().contains("of hooks"));
+                let hint = e.hint().unwrap();
+                assert!(hint.contains("agpm edit hooks") && !hint.contains('{'));
+            }
+            other => panic!("Expected the failed script of the project, got {other:?}"),
+        }
+        let hooks = pm.get_one("hooks").unwrap();
+        assert_eq!(pm.build_status(hooks), BuildStatus::Failed);
+        assert!(!pm.info("hooks").unwrap().dirty);
+        assert_eq!(pm.query(&failed), vec![hooks]);
+        // A repoint that its hooks stop doesn't leave the new download behind
+        let mut blocked = hooks.clone();
+        blocked.hooks.pre_update = vec!["exit 1".into()];
+        pm.edit("hooks", blocked.clone()).unwrap();
+        let moved = Project {
+            dir: "hooks_moved".into(),
+            ..blocked
+        };
+        assert!(pm.repoint("hooks", moved, None).is_err());
+        assert!(!pm.get_dirs().git().join("hooks_moved").exists());
+        assert!(src.join("hooks").exists());
+        pm.uninstall("hooks").unwrap();
+        assert!(src.join("hooks_post_uninstall").exists());
+        std::fs::remove_file(src.join("hooks_post_uninstall")).unwrap();
+    }
+
+    #[test]
+    fn exec() {
+        let (mut pm, repo) = setup();
+        let prj = repo.project("exec");
+        pm.install(prj).unwrap();
+        let run = |command: &[&str]| {
+            let command: Vec<String> = command.iter().map(|e| e.to_string()).collect();
+            pm.exec("exec", &command)
+        };
+        // It runs in the sources of the project
+        assert!(run(&["test", "-f", "dates.txt"]).unwrap().success());
+        assert!(!run(&["false"]).unwrap().success());
+        assert!(matches!(
+            pm.exec("missing", &[]),
+            Err(PMError::Common(amisgitpm::CommonPMErrors::NonExisting(_)))
+        ));
+        std::fs::remove_dir_all(pm.get_dirs().src().join("exec")).unwrap();
+        match run(&["true"]) {
+            Err(e @ PMError::Common(amisgitpm::CommonPMErrors::MissingSources(_))) => {
+                assert_eq!(e.class(), amisgitpm::ErrorClass::NotFound);
+                assert!(e.hint().unwrap().contains("exec"));
+            }
+            other => panic!("Expected the path of the missing sources, got {other:?}"),
+        }
+        match pm.info("exec") {
+            Err(PMError::Git(e)) => assert_eq!(e.project, "exec"),
+            other => panic!("Expected the git error of the project, got {other:?}"),
+        }
+        pm.get_mut_store().remove("exec").unwrap();
+    }
+
+    #[test]
+    fn parallel_update() {
+        let (mut pm, repo) = setup();
+        let lib = repo.project("parallel_lib");
+        // It sees the sources of its dependency when it's built
+        let app = Project {
+            name: "parallel_app".into(),
+            dir: "parallel_app".into(),
+            install_script: vec!["cp ../parallel_lib/dates.txt seen.txt".into()],
+            dependencies: vec!["parallel_lib".into()],
+            ..lib.clone()
+        };
+        let broken = Project {
+            name: "parallel_broken".into(),
+            dir: "parallel_broken".into(),
+            install_script: vec!["test ! -f ../broken".into()],
+            ..lib.clone()
+        };
+        // It fails before its sources are replaced
+        let mut unready = Project {
+            name: "parallel_unready".into(),
+            dir: "parallel_unready".into(),
+            ..lib.clone()
+        };
+        unready.hooks.pre_update = vec!["exit 1".into()];
+        // Dependents that aren't updated themselves, and are built again
+        let plugin = Project {
+            name: "parallel_plugin".into(),
+            dir: "parallel_plugin".into(),
+            install_script: vec!["test ! -f ../plugin_fails".into()],
+            dependencies: vec!["parallel_lib".into()],
+            ..lib.clone()
+        };
+        let tool = Project {
+            name: "parallel_tool".into(),
+            dir: "parallel_tool".into(),
+            dependencies: vec!["parallel_plugin".into()],
+            ..lib.clone()
+        };
+        let extra = Project {
+            name: "parallel_extra".into(),
+            dir: "parallel_extra".into(),
+            ..app.clone()
+        };
+        pm.install_many(vec![
+            app.clone(),
+            lib.clone(),
+            broken,
+            plugin,
+            tool,
+            extra,
+            unready,
+        ])
+        .unwrap();
+        repo.update();
+        std::fs::write(pm.get_dirs().src().join("broken"), "").unwrap();
+        std::fs::write(pm.get_dirs().src().join("plugin_fails"), "").unwrap();
+        let report = pm
+            .update_batch(&["parallel_app", "parallel_broken", "parallel_lib"])
+            .unwrap();
+        let names: Vec<&str> = report.outcomes.iter().map(|e| e.0.as_str()).collect();
+        assert_eq!(
+            names[..3],
+            ["parallel_lib", "parallel_broken", "parallel_app"]
+        );
+        assert_eq!(names.len(), 6);
+        // The fetched sources of the project that failed aren't left behind
+        let report_unready = pm.update_batch(&["parallel_unready"]).unwrap();
+        assert_eq!(report_unready.failed(), vec!["parallel_unready"]);
+        assert!(!pm.get_dirs().git().join("parallel_unready").exists());
+        let outcome = |name: &str| &report.outcomes.iter().find(|e| e.0 == name).unwrap().1;
+        assert!(matches!(outcome("parallel_lib"), Outcome::Done));
+        assert!(matches!(outcome("parallel_broken"), Outcome::Failed(_)));
+        assert!(matches!(outcome("parallel_app"), Outcome::Done));
+        assert!(matches!(outcome("parallel_plugin"), Outcome::Failed(_)));
+        assert!(matches!(outcome("parallel_extra"), Outcome::Done));
+        assert!(matches!(outcome("parallel_tool"), Outcome::Skipped(_)));
+        assert!(report.was_rebuilt("parallel_extra") && report.was_rebuilt("parallel_plugin"));
+        assert!(!report.was_rebuilt("parallel_app") && !report.was_rebuilt("parallel_lib"));
+        assert_eq!(report.failed(), vec!["parallel_broken", "parallel_plugin"]);
+        let src = pm.get_dirs().src();
+        for built in ["parallel_app", "parallel_extra"] {
+            assert_eq!(
+                std::fs::read(src.join(built).join("seen.txt")).unwrap(),
+                std::fs::read(repo.path().join("dates.txt")).unwrap()
+            );
+        }
+        std::fs::remove_file(src.join("broken")).unwrap();
+        std::fs::remove_file(src.join("plugin_fails")).unwrap();
+        pm.uninstall("parallel_unready").unwrap();
+        pm.uninstall("parallel_tool").unwrap();
+        pm.uninstall("parallel_plugin").unwrap();
+        pm.uninstall("parallel_extra").unwrap();
+        pm.uninstall("parallel_app").unwrap();
+        pm.uninstall("parallel_lib").unwrap();
+        pm.uninstall("parallel_broken").unwrap();
+    }
+
+    #[test]
+    fn batch_uninstall_restore() {
+        let (mut pm, repo) = setup();
+        let base = repo.project("batch_rm_base");
+        let app = Project {
+            name: "batch_rm_app".into(),
+            dir: "batch_rm_app".into(),
+            dependencies: vec!["batch_rm_base".into()],
+            uninstall_script: vec!["exit 1".into()],
+            ..base.clone()
+        };
+        let other = Project {
+            name: "batch_rm_other".into(),
+            dir: "batch_rm_other".into(),
+            ..base.clone()
+        };
+        pm.install_many(vec![base, app.clone(), other]).unwrap();
+        let names = ["batch_rm_base", "batch_rm_app", "batch_rm_other"];
+        let report = pm.restore_batch(&names).unwrap();
+        assert!(report
+            .outcomes
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, Outcome::Skipped(_))));
+        let report = pm.uninstall_batch(&names).unwrap();
+        let outcome = |name: &str| &report.outcomes.iter().find(|e| e.0 == name).unwrap().1;
+        assert!(matches!(outcome("batch_rm_app"), Outcome::Failed(_)));
+        assert!(matches!(outcome("batch_rm_base"), Outcome::Skipped(_)));
+        assert!(matches!(outcome("batch_rm_other"), Outcome::Done));
+        assert_eq!(report.failed(), vec!["batch_rm_app"]);
+        assert!(pm.get_one("batch_rm_base").is_some());
+        assert!(pm.get_one("batch_rm_other").is_none());
+        let fixed = Project {
+            uninstall_script: vec![],
+            ..app
+        };
+        pm.edit("batch_rm_app", fixed).unwrap();
+        let report = pm
+            .uninstall_batch(&["batch_rm_base", "batch_rm_app"])
+            .unwrap();
+        assert!(!report.has_failures());
+        assert!(pm.get_one("batch_rm_base").is_none());
     }
 }